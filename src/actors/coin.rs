@@ -14,41 +14,64 @@ use ggez::{Context, GameResult};
 use ggez::nalgebra as na;
 use ggez::nalgebra::{Isometry2};
 
-use actors::types::ActorType;
+use actors::types::{ActorType, CollisionObjectData};
 use game_inputs::{Direction, InputEvent};
+use layers::{Layer, LayerMembership};
 use ncollide::world::{CollisionObjectHandle, CollisionWorld2};
 
-/// Borrowed from GGEZ Astroblasto example
-/// This is used to transalte the world coordinate system which has both Y == 0
-/// and X == 0 being the origin (center of the screen), and converts it to the
-/// screen coordinate system which has the origin in the upper left of the
-/// screen with Y inverted (increasing in a downward direction).
-/// This helps with converting all items being rendred from the top-left.
-fn world_to_screen_coords(screen_width: u32, screen_height: u32, point: Vector2) -> Vector2 {
-    let width = screen_width as f32;
-    let height = screen_height as f32;
-    let x = point.x + width / 2.0;
-    let y = height - (point.y + height / 2.0);
-    Vector2::new(x, y)
+/// `CoinKind` is the type of a coin, which decides how many points it is worth.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum CoinKind {
+	Bronze,
+	Silver,
+	Gold,
+}
+
+impl CoinKind {
+	pub fn value(self) -> i32 {
+		match self {
+			CoinKind::Bronze => 100,
+			CoinKind::Silver => 500,
+			CoinKind::Gold => 1337,
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<CoinKind> {
+		match name {
+			"bronze" => Some(CoinKind::Bronze),
+			"silver" => Some(CoinKind::Silver),
+			"gold" => Some(CoinKind::Gold),
+			_ => None,
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			CoinKind::Bronze => "bronze",
+			CoinKind::Silver => "silver",
+			CoinKind::Gold => "gold",
+		}
+	}
 }
 
 #[derive(Clone, Copy)]
 pub struct Coin {
 	pub tag: ActorType,
 	pub pos: Vector2,
+	pub kind: CoinKind,
 	pickedup: bool,
 	debug: bool,
 	col_handle: Option<CollisionObjectHandle>,
 }
 
-/// `Coin` is an interactable object that can give points. Currently, points are
-/// handled in the main collision handle function; however, the points need to
-/// be implemented as a `Coin` attribute to reduce redundancy and simplify code.
+/// `Coin` is an interactable object that gives the points of its `CoinKind`
+/// when picked up.
 impl Coin {
-	pub fn new(pos: Vector2) -> Coin {
+	pub fn new(pos: Vector2, kind: CoinKind) -> Coin {
 		let mut coin = Coin {
 			tag: ActorType::Coin,
-			pos: Vector2::new(1920./2. - 750., -1080./4.),
+			pos: pos,
+			kind: kind,
 			pickedup: false,
 			debug: true,
 			col_handle: None,
@@ -57,17 +80,21 @@ impl Coin {
 		(coin)
 	}
 
+	/// The collision layers coins are on.
+	pub fn collision_layers() -> LayerMembership {
+		LayerMembership::new(&[Layer::Pickup])
+	}
+
 	// `update()` ensures the collision handle stays in the same location as the rendered coin object.
-	pub fn update(&mut self, ctx: &mut Context, world: &mut CollisionWorld2<f32, ()>) {
-		let position = world_to_screen_coords(ctx.conf.window_mode.width, ctx.conf.window_mode.height, self.pos);
-		world.set_position(self.col_handle.unwrap(), Isometry2::new(Vector2::new(position.x.clone(), position.y.clone()), 0.));
+	pub fn update(&mut self, world: &mut CollisionWorld2<f32, CollisionObjectData>) {
+		world.set_position(self.col_handle.unwrap(), Isometry2::new(self.pos, 0.));
 	}
 
 	pub fn set_col_handle(&mut self, col_handle: CollisionObjectHandle) {
 		self.col_handle = Some(col_handle);
 	}
 
-	pub fn getColHandle(&mut self) -> CollisionObjectHandle {
+	pub fn getColHandle(&self) -> CollisionObjectHandle {
 		self.col_handle.unwrap()
 	}
 
@@ -79,8 +106,13 @@ impl Coin {
 		self.pickedup = true;
 	}
 
-	pub fn isPickedUp(&mut self) -> bool {
+	pub fn isPickedUp(&self) -> bool {
 		self.pickedup
 	}
+
+	/// The points given for picking up this coin.
+	pub fn value(&self) -> i32 {
+		self.kind.value()
+	}
 }
 
@@ -0,0 +1,105 @@
+//! `Enemy` is one of Neferrious's minions. It patrols back and forth along the
+//! ground, hurts Ferris on contact, and is defeated by a ground pound.
+
+use ggez::graphics::Vector2;
+use ggez::nalgebra::{Isometry2};
+
+use actors::types::{ActorType, CollisionObjectData};
+use game_inputs::Direction;
+use layers::{Layer, LayerMembership};
+use ncollide::shape::{Cuboid2, ShapeHandle2};
+use ncollide::world::{CollisionObjectHandle, CollisionWorld2};
+
+/// Horizontal distance an enemy walks each step.
+const PATROL_SPEED: f32 = 3.;
+
+/// Scale of the enemy sprite (Ferris's sprite) and collision shape.
+pub const ENEMY_SCALE: f32 = 0.6;
+const HALF_WIDTH: f32 = 113. * ENEMY_SCALE;
+const HALF_HEIGHT: f32 = 83.5 * ENEMY_SCALE;
+
+/// Points awarded for defeating an enemy.
+pub const ENEMY_SCORE: i32 = 250;
+
+#[derive(Clone, Copy)]
+pub struct Enemy {
+	pub tag: ActorType,
+	pub pos: Vector2,
+	pub dir: Direction,
+	/// Furthest left and right x positions of the patrol.
+	patrol: (f32, f32),
+	defeated: bool,
+	col_handle: Option<CollisionObjectHandle>,
+}
+
+impl Enemy {
+	/// Creates an enemy at `pos` that patrols `distance` to either side of it.
+	pub fn new(pos: Vector2, distance: f32) -> Enemy {
+		Enemy {
+			tag: ActorType::Enemy,
+			pos: pos,
+			dir: Direction::Left,
+			patrol: (pos.x - distance, pos.x + distance),
+			defeated: false,
+			col_handle: None,
+		}
+	}
+
+	/// The collision layers enemies are on.
+	pub fn collision_layers() -> LayerMembership {
+		LayerMembership::new(&[Layer::Enemy])
+	}
+
+	pub fn collision_shape() -> ShapeHandle2<f32> {
+		ShapeHandle2::new(Cuboid2::new(Vector2::new(HALF_WIDTH, HALF_HEIGHT)))
+	}
+
+	/// `step()` walks the enemy along its patrol, turning around at either end.
+	pub fn step(&mut self) {
+		if self.defeated {
+			return;
+		}
+		self.pos.x += self.dir.movement() as f32 * PATROL_SPEED;
+		if self.pos.x <= self.patrol.0 {
+			self.pos.x = self.patrol.0;
+			self.dir = Direction::Right;
+		}
+		else if self.pos.x >= self.patrol.1 {
+			self.pos.x = self.patrol.1;
+			self.dir = Direction::Left;
+		}
+	}
+
+	// `update()` ensures the collision handle stays in the same location as the rendered enemy,
+	// and keeps the velocity in the collision object's data up to date.
+	pub fn update(&mut self, world: &mut CollisionWorld2<f32, CollisionObjectData>) {
+		world.set_position(self.col_handle.unwrap(), Isometry2::new(Vector2::new(self.pos.x + HALF_WIDTH, self.pos.y - HALF_HEIGHT), 0.));
+		if let Some(object) = world.collision_object(self.col_handle.unwrap()) {
+			object.data().set_velocity(self.velocity());
+		}
+	}
+
+	/// The distance the enemy walks each step, which is nothing once defeated.
+	pub fn velocity(&self) -> Vector2 {
+		if self.defeated {
+			return Vector2::new(0., 0.);
+		}
+		Vector2::new(self.dir.movement() as f32 * PATROL_SPEED, 0.)
+	}
+
+	pub fn set_col_handle(&mut self, col_handle: CollisionObjectHandle) {
+		self.col_handle = Some(col_handle);
+	}
+
+	pub fn getColHandle(&self) -> CollisionObjectHandle {
+		self.col_handle.unwrap()
+	}
+
+	pub fn defeat(&mut self) {
+		self.defeated = true;
+	}
+
+	pub fn is_defeated(&self) -> bool {
+		self.defeated
+	}
+}
@@ -6,5 +6,7 @@ pub mod types;
 pub mod step_queue;
 pub mod coin;
 pub mod object;
+pub mod enemy;
+pub mod status;
 
 use game_inputs::{InputEvent, Direction};
\ No newline at end of file
@@ -14,23 +14,15 @@ use ggez::{Context, GameResult};
 use ggez::nalgebra as na;
 use ggez::nalgebra::{Isometry2};
 
-use actors::types::ActorType;
+use actors::types::{ActorType, CollisionObjectData};
 use game_inputs::{Direction, InputEvent};
+use layers::{Layer, LayerMembership};
 use ncollide::world::{CollisionObjectHandle, CollisionWorld2};
 
-/// Borrowed from GGEZ Astroblasto example
-/// This is used to transalte the world coordinate system which has both Y == 0
-/// and X == 0 being the origin (center of the screen), and converts it to the
-/// screen coordinate system which has the origin in the upper left of the
-/// screen with Y inverted (increasing in a downward direction).
-/// This helps with converting all items being rendred from the top-left.
-fn world_to_screen_coords(screen_width: u32, screen_height: u32, point: Vector2) -> Vector2 {
-    let width = screen_width as f32;
-    let height = screen_height as f32;
-    let x = point.x + width / 2.0;
-    let y = height - (point.y + height / 2.0);
-    Vector2::new(x, y)
-}
+/// The half extents of the vending machine's sensor, and how far right of the
+/// object's position its center is.
+pub const SENSOR_HALF_EXTENTS: (f32, f32) = (200., 450.);
+const SENSOR_OFFSET: f32 = 250.;
 
 #[derive(Clone, Copy)]
 pub struct Object {
@@ -57,10 +49,20 @@ impl Object {
 		(object)
 	}
 
+	/// The collision layers objects are on. The vending machine is the level's goal, so
+	/// it is a trigger.
+	pub fn collision_layers() -> LayerMembership {
+		LayerMembership::new(&[Layer::Trigger])
+	}
+
+	/// The center of the sensor that finishes the level when the player touches it.
+	pub fn sensor_center(pos: Vector2) -> Vector2 {
+		Vector2::new(pos.x + SENSOR_OFFSET, pos.y)
+	}
+
 	// `update()` ensures the collision handle stays in the same location as the rendered coin object.
-	pub fn update(&mut self, ctx: &mut Context, world: &mut CollisionWorld2<f32, ()>) {
-		let position = world_to_screen_coords(ctx.conf.window_mode.width, ctx.conf.window_mode.height, self.pos);
-		world.set_position(self.col_handle.unwrap(), Isometry2::new(Vector2::new(position.x.clone() + 250., position.y.clone()), 0.));
+	pub fn update(&mut self, world: &mut CollisionWorld2<f32, CollisionObjectData>) {
+		world.set_position(self.col_handle.unwrap(), Isometry2::new(Object::sensor_center(self.pos), 0.));
 	}
 
 	pub fn set_col_handle(&mut self, col_handle: CollisionObjectHandle) {
@@ -82,5 +84,10 @@ impl Object {
 	pub fn isPickedUp(&mut self) -> bool {
 		self.pickedup
 	}
+
+	/// Resets the object so that it can be interacted with again.
+	pub fn release(&mut self) {
+		self.pickedup = false;
+	}
 }
 
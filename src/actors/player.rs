@@ -14,13 +14,18 @@ use ggez::{Context, GameResult};
 use ggez::nalgebra as na;
 use ggez::nalgebra::{Isometry2};
 
-use actors::types::ActorType;
+use actors::types::{ActorType, CollisionObjectData};
 use game_inputs::{Direction, InputEvent};
+use layers::{Layer, LayerMembership};
+use level::VolumeKind;
 use actors::step_queue::{StepQueue, Step};
+use actors::status::{EffectKind, PlayerStats, StatusEffect, StatusEffects};
+use ncollide::shape::{Cuboid2, ShapeHandle2};
 use ncollide::world::{CollisionObjectHandle, CollisionWorld2};
 
 
-/// Constants used for movement physics.
+/// Constants used for movement physics. These are the base values of the
+/// player's `PlayerStats`, before any status effects are applied.
 pub const STEP_PERIOD: f64 = 1.0 / 60.0;
 const MOVE_ACCEL: f64 = 150. * STEP_PERIOD;
 const STOP_ACCEL: f64 = 350. * STEP_PERIOD;
@@ -28,25 +33,71 @@ const FALL_ACCEL: f64 = 360. * STEP_PERIOD;
 const JUMP_SPEED: f64 = 60.;
 const MAX_FALL_SPEED: f64 = 40.;
 const MAX_MOVE_SPEED: f64 = 10.;
-fn jump_duration(x_speed: f64) -> f64 { 0.21 + 0.10 * (x_speed / MAX_MOVE_SPEED) } 
+const JUMP_TIME: f64 = 0.21;
+const JUMP_TIME_PER_SPEED: f64 = 0.10;
+fn jump_duration(x_speed: f64, stats: &PlayerStats) -> f64 { stats.jump_time + stats.jump_time_per_speed * (x_speed / stats.max_move_speed) } 
 
 const GRAPHIC_STEP_DURATION: f64 = 0.16;
 
-
-/// Borrowed from GGEZ Astroblasto example
-/// This is used to transalte the world coordinate system which has both Y == 0
-/// and X == 0 being the origin (center of the screen), and converts it to the
-/// screen coordinate system which has the origin in the upper left of the
-/// screen with Y inverted (increasing in a downward direction).
-/// This helps with converting all items being rendred from the top-left.
-fn world_to_screen_coords(screen_width: u32, screen_height: u32, point: Vector2) -> Vector2 {
-    let width = screen_width as f32;
-    let height = screen_height as f32;
-    let x = point.x + width / 2.0;
-    let y = height - (point.y + height / 2.0);
-    Vector2::new(x, y)
+/// Half extents of the player's collision shape at full size. The shape is
+/// centered on the sprite, which is 226 x 167 pixels.
+const HALF_WIDTH: f32 = 113.;
+const HALF_HEIGHT: f32 = 83.5;
+
+/// Maximum fall speed while sliding down a wall.
+const WALL_SLIDE_SPEED: f64 = 8.;
+
+/// Constants used for the dash and ground pound moves. A dash ignores
+/// `max_move_speed`, and a ground pound ignores `max_fall_speed`.
+const DASH_SPEED: f64 = 30.;
+const DASH_DURATION: f64 = 0.18;
+const DASH_COOLDOWN: f64 = 0.8;
+const GROUND_POUND_SPEED: f64 = 55.;
+/// Fraction of the jump speed the player bounces up with after pounding an enemy.
+const BOUNCE: f64 = 0.8;
+
+/// How long one-way platforms are ignored after dropping through one.
+const DROP_THROUGH_TIME: f64 = 0.25;
+
+/// Constants used for climbing ladders and vines. Each is a (vertical, sideways) speed.
+const LADDER_CLIMB_SPEED: (f64, f64) = (6., 2.);
+const VINE_CLIMB_SPEED: (f64, f64) = (4., 5.);
+/// Upwards speed given when climbing or swimming out of the top of a volume.
+const CLIMB_OUT_SPEED: f64 = 20.;
+
+/// Constants used for swimming. Buoyancy leaves `SWIM_GRAVITY` of `fall_accel`
+/// pulling the player down, and drag takes `WATER_DRAG` of its velocity every step.
+const SWIM_GRAVITY: f64 = 0.15;
+const WATER_DRAG: f64 = 0.08;
+const SWIM_MAX_SPEED: f64 = 6.;
+const SWIM_MAX_SINK_SPEED: f64 = 6.;
+/// Each press of jump in water is a stroke upwards. Strokes can be repeated
+/// as often as `SWIM_STROKE_COOLDOWN` allows.
+const SWIM_STROKE_SPEED: f64 = 14.;
+const SWIM_STROKE_COOLDOWN: f64 = 0.25;
+
+/// Speed the player flies at while noclip is on.
+const NOCLIP_SPEED: f64 = 15.;
+
+/// `base_stats()` returns the player's stats with no status effects applied.
+pub fn base_stats() -> PlayerStats {
+	PlayerStats {
+		move_accel: MOVE_ACCEL,
+		stop_accel: STOP_ACCEL,
+		fall_accel: FALL_ACCEL,
+		jump_speed: JUMP_SPEED,
+		max_fall_speed: MAX_FALL_SPEED,
+		max_move_speed: MAX_MOVE_SPEED,
+		jump_time: JUMP_TIME,
+		jump_time_per_speed: JUMP_TIME_PER_SPEED,
+		air_jumps: 0,
+		scale: 1.,
+		invincible: false,
+		shielded: false,
+	}
 }
 
+
 /// `Time` will be used to maintain event timing. This will be properly implemented
 /// in the future. I found that not having a time feature was causing some graphics
 /// and physics based anomalies, and found resources that explained how timing can
@@ -61,15 +112,41 @@ pub enum PlayerState{
 	Jumping,
 	Walking,
 	Idle,
+	Dashing,
+	GroundPounding,
+	Climbing,
+	Swimming,
+}
+
+/// `Abilities` are the movement abilities a level unlocks for the player.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Abilities {
+	/// One extra jump while in the air.
+	pub double_jump: bool,
+	/// Sliding down walls and jumping away from them.
+	pub wall_jump: bool,
 }
 
-///
+/// `TerrainContact` summarises how the player is touching the terrain after a
+/// collision world update, sorted by the direction of each contact normal.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct TerrainContact {
+	pub ground: bool,
+	/// The ground being stood on is a one-way platform.
+	pub on_platform: bool,
+	pub ceiling: bool,
+	/// The side of the player a wall is on, if any.
+	pub wall: Option<Direction>,
+}
+
+/// `PlayerSize` is the size of the player, which decides its collision shape.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub enum PlayerSize {
 	Big,
+	Small,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Player {
 	time: f64,
 	state_start_time: f64,
@@ -86,6 +163,25 @@ pub struct Player {
 	debug: bool,
 	col_handle: Option<CollisionObjectHandle>,
 	step_queue: StepQueue,
+	base_stats: PlayerStats,
+	stats: PlayerStats,
+	effects: StatusEffects,
+	air_jumps_used: u32,
+	/// The size of the collision shape currently in the collision world.
+	shape_size: PlayerSize,
+	abilities: Abilities,
+	terrain: TerrainContact,
+	dash_dir: Direction,
+	dash_time: f64,
+	dash_cooldown: f64,
+	drop_through_time: f64,
+	/// The volume the player is inside of, if any.
+	volume: Option<VolumeKind>,
+	/// The vertical direction held, used to climb and swim.
+	vertical: Option<Direction>,
+	stroke_cooldown: f64,
+	/// The player flies through everything, ignoring gravity and the terrain.
+	noclip: bool,
 }
 
 
@@ -95,7 +191,7 @@ impl Player {
 			time,
 			state_start_time: time,
 			tag: ActorType::Player,
-			pos: Vector2::new(-1920., 0.),
+			pos: pos,
 			dir: Direction::Right,
 			currentState: PlayerState::Jumping,
 			size: PlayerSize:: Big,
@@ -106,15 +202,65 @@ impl Player {
 			debug: true,
 			col_handle: None,
 			step_queue: StepQueue::new(),
+			base_stats: base_stats(),
+			stats: base_stats(),
+			effects: StatusEffects::new(),
+			air_jumps_used: 0,
+			shape_size: PlayerSize::Big,
+			abilities: Abilities::default(),
+			terrain: TerrainContact::default(),
+			dash_dir: Direction::Right,
+			dash_time: 0.,
+			dash_cooldown: 0.,
+			drop_through_time: 0.,
+			volume: None,
+			vertical: None,
+			stroke_cooldown: 0.,
+			noclip: false,
 		};
 		player.set_movement(move_dir);
 		(player)
 	}
 
 	// `update()` ensures the collision handle stays in the same location as the rendered coin object.
-	pub fn update(&mut self, ctx: &mut Context, world: &mut CollisionWorld2<f32, ()>) {
-		let position = world_to_screen_coords(ctx.conf.window_mode.width, ctx.conf.window_mode.height, self.pos);
-		world.set_position(self.col_handle.unwrap(), Isometry2::new(Vector2::new(position.x.clone(), position.y.clone()), 0.));
+	// If a status effect has changed the player's size, the collision object is replaced by one
+	// with the new shape. The velocity in the collision object's data is kept up to date too.
+	// `pos` is the top left of the sprite, so the shape's center is below and to the right of it.
+	pub fn update(&mut self, world: &mut CollisionWorld2<f32, CollisionObjectData>) {
+		let half = self.half_extents();
+		let isometry = Isometry2::new(Vector2::new(self.pos.x + half.x, self.pos.y - half.y), 0.);
+		if self.shape_size != self.size {
+			self.rebuild_collision_shape(world, isometry);
+		}
+		else {
+			world.set_position(self.col_handle.unwrap(), isometry);
+		}
+		if let Some(object) = world.collision_object(self.col_handle.unwrap()) {
+			object.data().set_velocity(self.velocity);
+		}
+	}
+
+	/// The collision layers the player is on.
+	pub fn collision_layers() -> LayerMembership {
+		LayerMembership::new(&[Layer::Player])
+	}
+
+	/// `collision_shape()` is the player's collision shape at its current size.
+	pub fn collision_shape(&self) -> ShapeHandle2<f32> {
+		ShapeHandle2::new(Cuboid2::new(self.half_extents()))
+	}
+
+	/// Replaces the player's collision object with one using the current collision shape,
+	/// keeping its collision groups, query type and data.
+	fn rebuild_collision_shape(&mut self, world: &mut CollisionWorld2<f32, CollisionObjectData>, isometry: Isometry2<f32>) {
+		let handle = self.col_handle.unwrap();
+		let (groups, query, data) = {
+			let object = world.collision_object(handle).unwrap();
+			(*object.collision_groups(), *object.query_type(), object.data().clone())
+		};
+		world.remove(&[handle]);
+		self.col_handle = Some(world.add(isometry, self.collision_shape(), groups, query, data));
+		self.shape_size = self.size;
 	}
 
 	pub fn set_col_handle(&mut self, col_handle: CollisionObjectHandle) {
@@ -125,6 +271,254 @@ impl Player {
 		self.col_handle.unwrap()
 	}
 
+	/// `set_abilities()` unlocks the given movement abilities. Double jump is granted as an
+	/// extra base air jump, so it stacks with the double jump status effect.
+	pub fn set_abilities(&mut self, abilities: Abilities) {
+		self.abilities = abilities;
+		self.base_stats.air_jumps = if abilities.double_jump { 1 } else { 0 };
+		self.refresh_stats();
+	}
+
+	pub fn abilities(&self) -> Abilities {
+		self.abilities
+	}
+
+	/// `set_base_stats()` replaces the player's movement tuning, such as after the tuning
+	/// file has been reloaded. The air jumps granted by abilities are kept.
+	pub fn set_base_stats(&mut self, stats: PlayerStats) {
+		let air_jumps = self.base_stats.air_jumps;
+		self.base_stats = stats;
+		self.base_stats.air_jumps = air_jumps;
+		self.refresh_stats();
+	}
+
+	/// `set_terrain_contact()` updates the player with the terrain it is touching after
+	/// the collision world has been updated. Touching the ground lands the player, losing
+	/// it starts a fall, and walls and ceilings stop movement into them.
+	pub fn set_terrain_contact(&mut self, contact: TerrainContact) {
+		if self.currentState == PlayerState::Swimming {
+			// The bottom of a pool stops the player sinking without ending the swim
+			if contact.ground && self.velocity.y < 0. {
+				self.velocity.y = 0.;
+			}
+		}
+		else if contact.ground && !self.grounded && self.velocity.y <= 0. {
+			self.input(InputEvent::Landed);
+		}
+		else if !contact.ground && self.grounded {
+			self.grounded = false;
+			// A dash carries on over the edge and falls once it ends
+			if self.currentState != PlayerState::Dashing {
+				self.currentState = PlayerState::Jumping;
+				let direction = self.dir;
+				self.set_movement(if self.moving { Some(direction) } else { None });
+			}
+		}
+		if contact.ceiling && self.velocity.y > 0. {
+			self.velocity.y = 0.;
+		}
+		if let Some(side) = contact.wall {
+			if (self.velocity.x as f64).signum() == side.movement() {
+				self.velocity.x = 0.;
+				if self.currentState == PlayerState::Dashing {
+					self.end_dash();
+				}
+			}
+		}
+		self.terrain = contact;
+	}
+
+	/// `set_volume()` updates the player with the volume it is inside of after the collision
+	/// world has been updated. Entering water starts a swim, and leaving the volume being
+	/// climbed or swum through drops the player back to falling.
+	pub fn set_volume(&mut self, volume: Option<VolumeKind>) {
+		self.volume = volume;
+		match (self.currentState, volume) {
+			(PlayerState::Swimming, Some(VolumeKind::Water)) => {},
+			(_, Some(VolumeKind::Water)) => self.start_swimming(),
+			(PlayerState::Swimming, _) => self.leave_volume(),
+			(PlayerState::Climbing, None) => self.leave_volume(),
+			_ => {},
+		}
+	}
+
+	pub fn is_climbing(&self) -> bool {
+		self.currentState == PlayerState::Climbing
+	}
+
+	pub fn is_swimming(&self) -> bool {
+		self.currentState == PlayerState::Swimming
+	}
+
+	/// Returns true while climbing or swimming, during which the player moves freely in
+	/// every direction instead of walking and jumping.
+	fn in_volume_state(&self) -> bool {
+		self.is_climbing() || self.is_swimming()
+	}
+
+	/// `start_climbing()` grabs hold of the ladder or vine the player is inside of.
+	fn start_climbing(&mut self) {
+		self.currentState = PlayerState::Climbing;
+		self.grounded = false;
+		self.air_jumps_used = 0;
+		self.velocity = na::zero();
+	}
+
+	/// `start_swimming()` ends any move in progress, and slows the player down as it hits the water.
+	fn start_swimming(&mut self) {
+		self.currentState = PlayerState::Swimming;
+		self.grounded = false;
+		self.air_jumps_used = 0;
+		self.dash_time = 0.;
+		self.velocity.y = self.velocity.y.max(-SWIM_MAX_SINK_SPEED as f32);
+	}
+
+	/// `leave_volume()` returns to falling after climbing or swimming out of a volume. Leaving
+	/// through the top gives a small hop, so the player can get up onto the ledge above.
+	fn leave_volume(&mut self) {
+		if self.vertical == Some(Direction::Up) || self.velocity.y > 0. {
+			self.velocity.y = self.velocity.y.max(CLIMB_OUT_SPEED as f32);
+		}
+		self.currentState = PlayerState::Jumping;
+		let direction = self.dir;
+		self.set_movement(if self.moving { Some(direction) } else { None });
+	}
+
+	/// Returns true if the player is standing on a one-way platform.
+	pub fn on_platform(&self) -> bool {
+		self.grounded && self.terrain.on_platform
+	}
+
+	/// Returns true while the player is falling through one-way platforms.
+	pub fn is_dropping_through(&self) -> bool {
+		self.drop_through_time > 0.
+	}
+
+	/// `half_extents()` are the half extents of the player's collision shape.
+	pub fn half_extents(&self) -> Vector2 {
+		Vector2::new(HALF_WIDTH * self.stats.scale, HALF_HEIGHT * self.stats.scale)
+	}
+
+	/// Returns true while the player is dashing or ground pounding, during which
+	/// the normal walking and jumping controls are ignored.
+	pub fn in_special_move(&self) -> bool {
+		self.currentState == PlayerState::Dashing || self.currentState == PlayerState::GroundPounding
+	}
+
+	pub fn is_ground_pounding(&self) -> bool {
+		self.currentState == PlayerState::GroundPounding
+	}
+
+	/// `bounce()` launches the player back up after ground pounding an enemy.
+	pub fn bounce(&mut self) {
+		self.grounded = false;
+		self.currentState = PlayerState::Jumping;
+		self.air_jumps_used = 0;
+		self.velocity.y = (self.stats.jump_speed * BOUNCE) as f32;
+		let direction = self.dir;
+		self.set_movement(if self.moving { Some(direction) } else { None });
+	}
+
+	/// `respawn()` puts the player back at `pos`, standing still and with no status effects.
+	pub fn respawn(&mut self, pos: Vector2) {
+		self.pos = pos;
+		self.velocity = na::zero();
+		self.grounded = false;
+		self.currentState = PlayerState::Jumping;
+		self.air_jumps_used = 0;
+		self.dash_time = 0.;
+		self.dash_cooldown = 0.;
+		self.drop_through_time = 0.;
+		self.stroke_cooldown = 0.;
+		self.effects.clear_timed();
+		self.refresh_stats();
+	}
+
+	/// `set_noclip()` turns noclip on or off. The player stops where it is either way,
+	/// and falls once noclip is turned off.
+	pub fn set_noclip(&mut self, noclip: bool) {
+		self.noclip = noclip;
+		self.velocity = na::zero();
+		self.grounded = false;
+		self.currentState = PlayerState::Jumping;
+	}
+
+	pub fn is_noclip(&self) -> bool {
+		self.noclip
+	}
+
+	/// Returns the side of a wall the player is sliding down, if wall jumping is unlocked
+	/// and the player is in the air against one.
+	pub fn wall_slide_side(&self) -> Option<Direction> {
+		if !self.abilities.wall_jump || self.grounded {
+			return None;
+		}
+		self.terrain.wall
+	}
+
+	/// `add_effect()` pushes a status effect onto the player's stack. Adding an effect the
+	/// player already has restarts its duration.
+	pub fn add_effect(&mut self, effect: StatusEffect) {
+		self.effects.push(effect);
+		self.refresh_stats();
+	}
+
+	/// Removes a status effect, returning true if it was active.
+	pub fn remove_effect(&mut self, kind: EffectKind) -> bool {
+		let removed = self.effects.remove(kind);
+		self.refresh_stats();
+		removed
+	}
+
+	pub fn has_effect(&self, kind: EffectKind) -> bool {
+		self.effects.has(kind)
+	}
+
+	pub fn effects(&self) -> &StatusEffects {
+		&self.effects
+	}
+
+	pub fn stats(&self) -> &PlayerStats {
+		&self.stats
+	}
+
+	pub fn size(&self) -> PlayerSize {
+		self.size
+	}
+
+	pub fn is_invincible(&self) -> bool {
+		self.stats.invincible
+	}
+
+	/// Removes the player's shield, returning true if there was one to absorb a hit.
+	pub fn consume_shield(&mut self) -> bool {
+		self.remove_effect(EffectKind::Shield)
+	}
+
+	/// `refresh_stats()` rebuilds the player's stats from the base stats and the active
+	/// status effects, and updates the player's size to match.
+	fn refresh_stats(&mut self) {
+		self.stats = self.effects.apply(&self.base_stats);
+		self.size = if self.stats.scale < 1. { PlayerSize::Small } else { PlayerSize::Big };
+		if self.air_jumps_used > self.stats.air_jumps && !self.grounded {
+			self.air_jumps_used = self.stats.air_jumps;
+		}
+	}
+
+	/// Returns true if pressing jump right now would make the player jump.
+	pub fn can_jump(&self) -> bool {
+		if self.in_special_move() {
+			return false;
+		}
+		if self.is_climbing() {
+			return true;
+		}
+		if self.is_swimming() {
+			return self.stroke_cooldown <= 0.;
+		}
+		self.grounded || self.wall_slide_side().is_some() || self.air_jumps_used < self.stats.air_jumps
+	}
+
 	/// `unput()` is a function for the `InputEvent` handler.
 	pub fn input(&mut self, event:InputEvent) {
 		match event {
@@ -132,6 +526,69 @@ impl Player {
 			_ => {},
 		}
 		match event {
+			// With noclip on, the held directions fly the player around and every move is ignored
+			InputEvent::UpdateMovement(direction) if self.noclip => self.moving = direction.is_some(),
+			InputEvent::UpdateVertical(direction) if self.noclip => self.vertical = direction,
+			_ if self.noclip => {}
+			// Held directions are still tracked during a dash or ground pound, and are
+			// picked back up once the move ends
+			InputEvent::UpdateMovement(direction) if self.in_special_move() || self.in_volume_state() => {
+				self.moving = direction.is_some();
+			}
+			InputEvent::PressJump if self.in_special_move() => {}
+			// Player pressed Jump while climbing, which jumps off the ladder or vine
+			InputEvent::PressJump if self.is_climbing() => {
+				self.currentState = PlayerState::Jumping;
+				self.air_jump();
+				let direction = self.dir;
+				self.set_movement(if self.moving { Some(direction) } else { None });
+			}
+			// Player pressed Jump while swimming, which is a stroke upwards
+			InputEvent::PressJump if self.is_swimming() => {
+				if self.stroke_cooldown <= 0. {
+					self.stroke_cooldown = SWIM_STROKE_COOLDOWN;
+					self.velocity.y = SWIM_STROKE_SPEED as f32;
+				}
+			}
+			// Player pressed or released Up or Down. Either grabs hold of a ladder or vine,
+			// and Down in the air is a ground pound.
+			InputEvent::UpdateVertical(direction) => {
+				self.vertical = direction;
+				let climbable = self.volume.map_or(false, |v| v.is_climbable());
+				if direction.is_some() && climbable && !self.in_special_move() && !self.in_volume_state() {
+					self.start_climbing();
+				}
+				else if direction == Some(Direction::Down) {
+					self.input(InputEvent::GroundPound);
+				}
+			}
+			// Player pressed Dash
+			InputEvent::Dash => {
+				if self.dash_cooldown <= 0. && !self.in_special_move() && !self.in_volume_state() {
+					self.currentState = PlayerState::Dashing;
+					self.dash_dir = self.dir;
+					self.dash_time = DASH_DURATION;
+					self.dash_cooldown = DASH_COOLDOWN;
+					self.velocity.y = 0.;
+				}
+			}
+			// Player pressed Down + Jump while standing on a one-way platform
+			InputEvent::DropThrough => {
+				if self.on_platform() && !self.in_special_move() {
+					self.drop_through_time = DROP_THROUGH_TIME;
+					self.grounded = false;
+					self.currentState = PlayerState::Jumping;
+					let direction = self.dir;
+					self.set_movement(if self.moving { Some(direction) } else { None });
+				}
+			}
+			// Player pressed Down while in the air
+			InputEvent::GroundPound => {
+				if !self.grounded && !self.in_special_move() && !self.in_volume_state() {
+					self.currentState = PlayerState::GroundPounding;
+					self.velocity = Vector2::new(0., -GROUND_POUND_SPEED as f32);
+				}
+			}
 			// No movement update (used for key up)
 			InputEvent::UpdateMovement(None) => {
 					self.currentState = PlayerState::Idle;
@@ -158,6 +615,13 @@ impl Player {
 					self.jump();
 					self.advance();
 				}
+				else if let Some(side) = self.wall_slide_side() {
+					self.wall_jump(side);
+				}
+				else if !self.grounded && self.air_jumps_used < self.stats.air_jumps {
+					self.air_jumps_used += 1;
+					self.air_jump();
+				}
 			}
 			// Initially used for timed updates, but was causing issues. Kept in case
 			// it is needed for future implementation
@@ -169,6 +633,7 @@ impl Player {
 				if !self.grounded{
 					self.velocity.y = 0.;
 					self.grounded = true;;
+					self.air_jumps_used = 0;
 					let direction = self.dir;
 					if self.moving {
 						self.currentState = PlayerState::Walking;
@@ -215,10 +680,29 @@ impl Player {
 	/// `step()` calculates the velocity of the player character and updates the new position
 	/// based on the velocity and the current (previous) location of the player character.
     pub fn step(&mut self) {
-    	let stop_accel = if self.grounded {STOP_ACCEL} else { MOVE_ACCEL };
+    	if self.effects.tick(STEP_PERIOD) {
+    		self.refresh_stats();
+    	}
+    	self.dash_cooldown -= STEP_PERIOD;
+    	self.drop_through_time -= STEP_PERIOD;
+
+    	if self.noclip {
+    		return self.noclip_step();
+    	}
+
+    	match self.currentState {
+    		PlayerState::Dashing => return self.dash_step(),
+    		PlayerState::GroundPounding => return self.ground_pound_step(),
+    		PlayerState::Climbing => return self.climb_step(),
+    		PlayerState::Swimming => return self.swim_step(),
+    		_ => {},
+    	}
+
+    	let move_accel = self.stats.move_accel;
+    	let stop_accel = if self.grounded { self.stats.stop_accel } else { move_accel };
     	let rel_vel_x = if self.velocity.x != na::zero() {self.velocity.x} else { 0.0 };
     	let rel_vel_x = if self.moving {
-    	let accel = if self.dir.movement() == ((rel_vel_x as f64).signum()) { MOVE_ACCEL } else { stop_accel };
+    	let accel = if self.dir.movement() == ((rel_vel_x as f64).signum()) { move_accel } else { stop_accel };
     	rel_vel_x + (self.dir.movement() * accel) as f32
     	}
     	else if self.grounded {
@@ -230,7 +714,13 @@ impl Player {
     	self.velocity.x = rel_vel_x;
 
     	if self.time > self.jump_time || !self.grounded {
-    		self.velocity.y -= FALL_ACCEL as f32;
+    		self.velocity.y -= self.stats.fall_accel as f32;
+    	}
+    	// Pressing against a wall slows the fall down to a slide
+    	if let Some(side) = self.wall_slide_side() {
+    		if self.moving && self.dir == side {
+    			self.velocity.y = self.velocity.y.max(-WALL_SLIDE_SPEED as f32);
+    		}
     	}
        	self.pos.x = self.pos.x + self.velocity.x;   
         self.pos.y = self.pos.y + self.velocity.y;
@@ -244,8 +734,8 @@ impl Player {
     	if self.grounded { 
 	    	self.grounded = false;
 	    	self.state_start_time = self.time;
-	    	self.jump_time = self.time + jump_duration((self.velocity.x).abs() as f64);
-	    	self.velocity.y = JUMP_SPEED as f32;
+	    	self.jump_time = self.time + jump_duration((self.velocity.x).abs() as f64, &self.stats);
+	    	self.velocity.y = self.stats.jump_speed as f32;
 
 	    	let direction = self.dir;
 					if self.moving {
@@ -259,17 +749,106 @@ impl Player {
 
     }
 
+    /// `air_jump()` restarts the jump velocity while the player is already in the
+    /// air, as granted by `EffectKind::DoubleJump`.
+    fn air_jump(&mut self) {
+    	self.state_start_time = self.time;
+    	self.jump_time = self.time + jump_duration((self.velocity.x).abs() as f64, &self.stats);
+    	self.velocity.y = self.stats.jump_speed as f32;
+    	self.step_queue.peek_specific(Step::Player);
+    }
+
+    /// `dash_step()` moves the player in a straight horizontal line for the length of the dash.
+    fn dash_step(&mut self) {
+    	self.velocity = Vector2::new((self.dash_dir.movement() * DASH_SPEED) as f32, 0.);
+    	self.pos.x = self.pos.x + self.velocity.x;
+    	self.dash_time -= STEP_PERIOD;
+    	if self.dash_time <= 0. {
+    		self.end_dash();
+    	}
+    }
+
+    /// `end_dash()` returns to walking, standing or falling depending on where the dash ended,
+    /// slowing the player back down to `max_move_speed`.
+    fn end_dash(&mut self) {
+    	self.dash_time = 0.;
+    	self.currentState = if !self.grounded {
+    		PlayerState::Jumping
+    	}
+    	else if self.moving {
+    		PlayerState::Walking
+    	}
+    	else {
+    		PlayerState::Idle
+    	};
+    	let direction = self.dir;
+    	self.set_movement(if self.moving { Some(direction) } else { None });
+    	self.update_movement();
+    }
+
+    /// `ground_pound_step()` drops the player straight down until it lands.
+    fn ground_pound_step(&mut self) {
+    	self.velocity = Vector2::new(0., -GROUND_POUND_SPEED as f32);
+    	self.pos.y = self.pos.y + self.velocity.y;
+    }
+
+    /// `climb_step()` moves the player along the ladder or vine it is holding on to. There is
+    /// no gravity while climbing, so letting go of the keys holds the player in place.
+    fn climb_step(&mut self) {
+    	let (climb_speed, side_speed) = match self.volume {
+    		Some(VolumeKind::Vine) => VINE_CLIMB_SPEED,
+    		_ => LADDER_CLIMB_SPEED,
+    	};
+    	let vertical = self.vertical.map_or(0., |d| d.vertical());
+    	let horizontal = if self.moving { self.dir.movement() } else { 0. };
+    	self.velocity = Vector2::new((horizontal * side_speed) as f32, (vertical * climb_speed) as f32);
+    	self.pos += self.velocity;
+    }
+
+    /// `noclip_step()` flies the player in the held directions at a constant speed.
+    fn noclip_step(&mut self) {
+    	let x = if self.moving { self.dir.movement() } else { 0. };
+    	let y = self.vertical.map_or(0., |v| v.vertical());
+    	self.velocity = Vector2::new((x * NOCLIP_SPEED) as f32, (y * NOCLIP_SPEED) as f32);
+    	self.pos += self.velocity;
+    }
+
+    /// `swim_step()` accelerates the player in the held directions, against the little gravity
+    /// left after buoyancy, and slows it down with the water's drag.
+    fn swim_step(&mut self) {
+    	self.stroke_cooldown -= STEP_PERIOD;
+    	let vertical = self.vertical.map_or(0., |d| d.vertical());
+    	let horizontal = if self.moving { self.dir.movement() } else { 0. };
+    	self.velocity.x += (horizontal * self.stats.move_accel) as f32;
+    	self.velocity.y += (vertical * self.stats.move_accel - self.stats.fall_accel * SWIM_GRAVITY) as f32;
+    	self.velocity *= (1. - WATER_DRAG) as f32;
+
+    	self.velocity.x = self.velocity.x.max(-SWIM_MAX_SPEED as f32).min(SWIM_MAX_SPEED as f32);
+    	self.velocity.y = self.velocity.y.max(-SWIM_MAX_SINK_SPEED as f32);
+    	self.pos += self.velocity;
+    }
+
+    /// `wall_jump()` jumps up and away from the wall on the given side of the player.
+    fn wall_jump(&mut self, wall: Direction) {
+    	let away = wall.opposite();
+    	self.air_jump();
+    	self.dir = away;
+    	self.velocity.x = (away.movement() * self.stats.max_move_speed) as f32;
+    	self.terrain.wall = None;
+    }
+
     /// `update_movement()` ensures the vertical and horizontal velocity of the player character
-    /// doesn't exceed the `MAX_MOVE_SPEED` and `MAX_FALL_SPEED` restrictions.
+    /// doesn't exceed the `max_move_speed` and `max_fall_speed` stats.
     /// Since we are modifying the coordinate system of the game for everything originating from
     /// the top left pixel, Y axis increases as it goes down, so we inverted the fall speed.
     fn update_movement(&mut self) {
-    	self.velocity.x = self.velocity.x.max(-MAX_MOVE_SPEED as f32);
+    	let max_move_speed = self.stats.max_move_speed;
+    	self.velocity.x = self.velocity.x.max(-max_move_speed as f32);
 
-    	self.velocity.x = self.velocity.x.min(MAX_MOVE_SPEED as f32);
+    	self.velocity.x = self.velocity.x.min(max_move_speed as f32);
 
     	if self.currentState == PlayerState::Jumping{
-    		self.velocity.y = self.velocity.y.max(-MAX_FALL_SPEED as f32);
+    		self.velocity.y = self.velocity.y.max(-self.stats.max_fall_speed as f32);
     	}
     	else {
     		self.velocity.y = 0.;
@@ -293,9 +872,26 @@ impl Player {
     			self.update_movement();
     		},
     		(true, false, true) => {
-    			self.velocity.y = -MAX_FALL_SPEED as f32;
+    			self.velocity.y = -self.stats.max_fall_speed as f32;
     		},
     		_ => {},
     	}
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn respawn_keeps_permanent_effects() {
+		let mut player = Player::new(Vector2::new(0., 0.), 1., None);
+		player.add_effect(StatusEffect::permanent(EffectKind::DoubleJump));
+		player.add_effect(StatusEffect::timed(EffectKind::SpeedUp, 10.));
+		let air_jumps = player.stats().air_jumps;
+		player.respawn(Vector2::new(100., 0.));
+		assert!(player.has_effect(EffectKind::DoubleJump));
+		assert!(!player.has_effect(EffectKind::SpeedUp));
+		assert_eq!(player.stats().air_jumps, air_jumps);
+		assert_eq!(player.stats().max_move_speed, base_stats().max_move_speed);
+	}
+}
@@ -0,0 +1,168 @@
+//! `status` contains the player's runtime movement stats and the status
+//! effects that alter them. The player keeps a stack of active effects, and
+//! its stats are rebuilt from the base values whenever an effect is added or
+//! expires, so an expired effect never leaves anything behind.
+
+/// `PlayerStats` are the movement values used by `Player::step()`. The base
+/// values come from the constants in `player.rs`, or the tuning profile in use.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayerStats {
+	pub move_accel: f64,
+	pub stop_accel: f64,
+	pub fall_accel: f64,
+	pub jump_speed: f64,
+	pub max_fall_speed: f64,
+	pub max_move_speed: f64,
+	/// How long jump is held for a standing jump, in seconds, and how much
+	/// longer it is held at `max_move_speed`.
+	pub jump_time: f64,
+	pub jump_time_per_speed: f64,
+	/// Extra jumps the player can make before landing.
+	pub air_jumps: u32,
+	/// Scale of the player's sprite and collision shape.
+	pub scale: f32,
+	pub invincible: bool,
+	pub shielded: bool,
+}
+
+/// `EffectKind` is an enumerated value of the available status effects.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum EffectKind {
+	/// Increases the acceleration and top speed of the player.
+	SpeedUp,
+	/// Increases the jump speed of the player.
+	HighJump,
+	/// Shrinks the player's sprite and collision shape.
+	Small,
+	/// The player can't be hurt.
+	Invincible,
+	/// Allows one extra jump while in the air.
+	DoubleJump,
+	/// Protects the player from the next hit.
+	Shield,
+}
+
+const SPEED_UP: f64 = 1.6;
+const HIGH_JUMP: f64 = 1.35;
+const SMALL_SCALE: f32 = 0.5;
+
+impl EffectKind {
+	/// The name of the effect in the string tables, as `effect.<key>`.
+	pub fn key(self) -> &'static str {
+		match self {
+			EffectKind::SpeedUp => "speed_up",
+			EffectKind::HighJump => "high_jump",
+			EffectKind::Small => "small",
+			EffectKind::Invincible => "invincible",
+			EffectKind::DoubleJump => "double_jump",
+			EffectKind::Shield => "shield",
+		}
+	}
+
+	/// Applies the effect to `stats`.
+	fn apply(self, stats: &mut PlayerStats) {
+		match self {
+			EffectKind::SpeedUp => {
+				stats.move_accel *= SPEED_UP;
+				stats.max_move_speed *= SPEED_UP;
+			}
+			EffectKind::HighJump => stats.jump_speed *= HIGH_JUMP,
+			EffectKind::Small => stats.scale *= SMALL_SCALE,
+			EffectKind::Invincible => stats.invincible = true,
+			EffectKind::DoubleJump => stats.air_jumps += 1,
+			EffectKind::Shield => stats.shielded = true,
+		}
+	}
+}
+
+/// A status effect that either lasts for a limited number of seconds or for
+/// the rest of the level.
+#[derive(Clone, Copy, Debug)]
+pub struct StatusEffect {
+	pub kind: EffectKind,
+	/// Seconds left before the effect expires, or `None` if it is permanent.
+	pub remaining: Option<f64>,
+}
+
+impl StatusEffect {
+	pub fn permanent(kind: EffectKind) -> StatusEffect {
+		StatusEffect { kind, remaining: None }
+	}
+
+	pub fn timed(kind: EffectKind, duration: f64) -> StatusEffect {
+		StatusEffect { kind, remaining: Some(duration) }
+	}
+
+	pub fn is_permanent(&self) -> bool {
+		self.remaining.is_none()
+	}
+
+	pub fn is_expired(&self) -> bool {
+		match self.remaining {
+			Some(remaining) => remaining <= 0.,
+			None => false,
+		}
+	}
+}
+
+/// `StatusEffects` is the stack of effects currently applied to the player.
+/// Adding an effect of a kind that is already active replaces it, which
+/// restarts its duration rather than stacking it twice.
+#[derive(Clone, Debug)]
+pub struct StatusEffects {
+	effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+	pub fn new() -> StatusEffects {
+		StatusEffects { effects: Vec::new() }
+	}
+
+	pub fn push(&mut self, effect: StatusEffect) {
+		self.remove(effect.kind);
+		self.effects.push(effect);
+	}
+
+	/// Removes the effect of the given kind, returning true if it was active.
+	pub fn remove(&mut self, kind: EffectKind) -> bool {
+		let before = self.effects.len();
+		self.effects.retain(|e| e.kind != kind);
+		self.effects.len() != before
+	}
+
+	/// Removes every timed effect, keeping the permanent ones, which were
+	/// bought to last the whole level.
+	pub fn clear_timed(&mut self) {
+		self.effects.retain(|e| e.is_permanent());
+	}
+
+	pub fn has(&self, kind: EffectKind) -> bool {
+		self.effects.iter().any(|e| e.kind == kind)
+	}
+
+	pub fn iter(&self) -> ::std::slice::Iter<StatusEffect> {
+		self.effects.iter()
+	}
+
+	/// Counts every timed effect down by `dt` seconds and removes the ones
+	/// that have run out. Returns true if any effect expired.
+	pub fn tick(&mut self, dt: f64) -> bool {
+		for effect in self.effects.iter_mut() {
+			if let Some(ref mut remaining) = effect.remaining {
+				*remaining -= dt;
+			}
+		}
+		let before = self.effects.len();
+		self.effects.retain(|e| !e.is_expired());
+		self.effects.len() != before
+	}
+
+	/// Builds the stats that result from applying every active effect to `base`.
+	pub fn apply(&self, base: &PlayerStats) -> PlayerStats {
+		let mut stats = *base;
+		for effect in &self.effects {
+			effect.kind.apply(&mut stats);
+		}
+		stats
+	}
+}
@@ -3,21 +3,51 @@
 use std::cell::Cell;
 use ggez::nalgebra as na;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub enum ActorType {
 	Player,
 	Coin,
     Object,
+    Enemy,
+    Terrain,
+    /// A volume or checkpoint of the level.
+    Zone,
 }
 
+impl ActorType {
+    pub fn all() -> [ActorType; 6] {
+        [ActorType::Player, ActorType::Coin, ActorType::Object, ActorType::Enemy, ActorType::Terrain, ActorType::Zone]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ActorType::Player => "player",
+            ActorType::Coin => "coin",
+            ActorType::Object => "object",
+            ActorType::Enemy => "enemy",
+            ActorType::Terrain => "terrain",
+            ActorType::Zone => "zone",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ActorType> {
+        ActorType::all().iter().cloned().find(|actor| actor.name() == name)
+    }
+}
+
+/// `CollisionObjectData` is attached to every object in the collision world,
+/// so that contact handlers can tell who hit what from the objects themselves.
+/// `id` is the index of the entity in the game state's list for its
+/// `ActorType`, and `velocity` is kept up to date for moving actors.
 #[derive(Clone, Debug)]
 pub struct CollisionObjectData {
-    pub name: &'static str,
+    pub actor: ActorType,
+    pub id: usize,
     pub velocity: Option<Cell<na::Vector2<f32>>>,
 }
 
 impl CollisionObjectData {
-    pub fn new(name: &'static str, velocity: Option<na::Vector2<f32>>) -> CollisionObjectData {
+    pub fn new(actor: ActorType, id: usize, velocity: Option<na::Vector2<f32>>) -> CollisionObjectData {
         let init_velocity;
         if let Some(velocity) = velocity {
             init_velocity = Some(Cell::new(velocity))
@@ -26,8 +56,21 @@ impl CollisionObjectData {
         }
 
         CollisionObjectData {
-            name: name,
+            actor: actor,
+            id: id,
             velocity: init_velocity,
         }
     }
-}
\ No newline at end of file
+
+    /// The velocity of the actor, if it moves.
+    pub fn velocity(&self) -> Option<na::Vector2<f32>> {
+        self.velocity.as_ref().map(|v| v.get())
+    }
+
+    /// Updates the velocity of a moving actor. Does nothing for static actors.
+    pub fn set_velocity(&self, velocity: na::Vector2<f32>) {
+        if let Some(ref v) = self.velocity {
+            v.set(velocity);
+        }
+    }
+}
@@ -0,0 +1,298 @@
+//! `heatmap` renders heatmaps of recorded play sessions over the layout of a
+//! level, so level designers can see which routes players take, where they
+//! jump and where they die. It runs headless, without opening a window:
+//!
+//! ```text
+//! cargo run --bin heatmap -- <level file> <session.jsonl>... [--out <dir>] [--radius <pixels>] [--no-background]
+//! ```
+//!
+//! Sessions are the telemetry files the game records with `--telemetry`, and
+//! only the runs of the given level are used. Three images are written to
+//! the output directory, named after the level: `<name>-positions.png`,
+//! `<name>-jumps.png` and `<name>-deaths.png`.
+
+extern crate game;
+extern crate ggez;
+extern crate image;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use ggez::graphics::Vector2;
+use image::{Rgba, RgbaImage};
+
+use game::actors::player::Player;
+use game::coords::{Camera, ScreenPos, WorldPos};
+use game::level::{Level, TerrainKind, VolumeKind};
+use game::telemetry::TelemetryEvent;
+
+/// The size of the game's window, which the level is laid out for.
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+
+/// How far each recorded point spreads its heat, in pixels.
+const DEFAULT_RADIUS: f32 = 40.;
+
+/// The colour of the level when it has no background image.
+const EMPTY_BACKGROUND: [u8; 4] = [30, 30, 30, 255];
+
+struct Options {
+    level: PathBuf,
+    sessions: Vec<PathBuf>,
+    out: PathBuf,
+    radius: f32,
+    background: bool,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: heatmap <level file> <session.jsonl>... [--out <dir>] [--radius <pixels>] [--no-background]");
+    process::exit(2);
+}
+
+fn parse_options() -> Options {
+    let mut positional = Vec::new();
+    let mut out = PathBuf::from(".");
+    let mut radius = DEFAULT_RADIUS;
+    let mut background = true;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => out = PathBuf::from(args.next().unwrap_or_else(|| usage())),
+            "--radius" => {
+                radius = args.next().and_then(|r| r.parse().ok()).filter(|&r: &f32| r > 0.).unwrap_or_else(|| usage())
+            }
+            "--no-background" => background = false,
+            "--help" | "-h" => usage(),
+            _ => positional.push(PathBuf::from(arg)),
+        }
+    }
+    if positional.len() < 2 {
+        usage();
+    }
+    let level = positional.remove(0);
+    Options { level, sessions: positional, out, radius, background }
+}
+
+/// `Points` are the player positions of every kind of event, in world
+/// coordinates at the center of the player.
+#[derive(Default)]
+struct Points {
+    positions: Vec<Vector2>,
+    jumps: Vec<Vector2>,
+    deaths: Vec<Vector2>,
+}
+
+/// Reads the events of `level_name`'s runs from a session file. Events
+/// before the first run start are assumed to be from the level too.
+fn read_session(path: &Path, level_name: &str, points: &mut Points) -> Result<(), String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    // Recorded positions are the top left of the player's sprite
+    let half = Player::new(Vector2::new(0., 0.), 0., None).half_extents();
+    let center = |pos: Vector2| Vector2::new(pos.x + half.x, pos.y - half.y);
+
+    let mut in_level = true;
+    let mut skipped = 0;
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        let event = match TelemetryEvent::parse(line) {
+            Some((_, event)) => event,
+            None => {
+                skipped += 1;
+                continue;
+            }
+        };
+        match event {
+            TelemetryEvent::RunStart { ref level } => in_level = level == level_name,
+            _ if !in_level => {}
+            TelemetryEvent::Position { pos } => points.positions.push(center(pos)),
+            TelemetryEvent::Jump { pos } => points.jumps.push(center(pos)),
+            TelemetryEvent::Death { pos, .. } => points.deaths.push(center(pos)),
+            _ => {}
+        }
+    }
+    if skipped > 0 {
+        eprintln!("{}: skipped {} unreadable lines", path.display(), skipped);
+    }
+    Ok(())
+}
+
+/// Blends `color` over the pixel at `x`, `y`, if it is inside the image.
+fn blend(image: &mut RgbaImage, x: i32, y: i32, color: [u8; 4]) {
+    if x < 0 || y < 0 || x >= image.width() as i32 || y >= image.height() as i32 {
+        return;
+    }
+    let pixel = image.get_pixel_mut(x as u32, y as u32);
+    let alpha = color[3] as f32 / 255.;
+    for i in 0..3 {
+        pixel.data[i] = (pixel.data[i] as f32 * (1. - alpha) + color[i] as f32 * alpha) as u8;
+    }
+    pixel.data[3] = 255;
+}
+
+/// Fills the rectangle with `center` and `half_extents`, given in screen pixels.
+fn fill_rect(image: &mut RgbaImage, center: ScreenPos, half_extents: Vector2, color: [u8; 4]) {
+    let center = center.0;
+    let (x0, x1) = ((center.x - half_extents.x) as i32, (center.x + half_extents.x) as i32);
+    let (y0, y1) = ((center.y - half_extents.y) as i32, (center.y + half_extents.y) as i32);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            blend(image, x, y, color);
+        }
+    }
+}
+
+/// Fills a convex polygon, given in screen pixels.
+fn fill_convex(image: &mut RgbaImage, points: &[Vector2], color: [u8; 4]) {
+    let min_x = points.iter().fold(f32::MAX, |m, p| m.min(p.x)) as i32;
+    let max_x = points.iter().fold(f32::MIN, |m, p| m.max(p.x)) as i32;
+    let min_y = points.iter().fold(f32::MAX, |m, p| m.min(p.y)) as i32;
+    let max_y = points.iter().fold(f32::MIN, |m, p| m.max(p.y)) as i32;
+    for y in min_y..max_y + 1 {
+        for x in min_x..max_x + 1 {
+            let p = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+            // Inside if on the same side of every edge
+            let sides: Vec<f32> = (0..points.len()).map(|i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+            }).collect();
+            if sides.iter().all(|&s| s >= 0.) || sides.iter().all(|&s| s <= 0.) {
+                blend(image, x, y, color);
+            }
+        }
+    }
+}
+
+/// Draws the level's background and layout, the way the game lays it out.
+fn draw_level(level: &Level, resources: &Path, background: bool, camera: &Camera) -> RgbaImage {
+    let mut image = RgbaImage::from_pixel(WIDTH, HEIGHT, Rgba(EMPTY_BACKGROUND));
+    if background {
+        let path = resources.join(level.background.trim_start_matches('/'));
+        match image::open(&path) {
+            // The game draws the background from the top left, unscaled
+            Ok(bg) => {
+                let bg = bg.to_rgba();
+                for (x, y, pixel) in bg.enumerate_pixels().filter(|&(x, y, _)| x < WIDTH && y < HEIGHT) {
+                    image.put_pixel(x, y, *pixel);
+                }
+            }
+            Err(e) => eprintln!("Unable to load the background {}: {}", path.display(), e),
+        }
+    }
+    let screen = |pos: Vector2| camera.world_to_screen(WorldPos(pos));
+
+    for block in &level.ground {
+        let color = match block.kind {
+            TerrainKind::Solid => [194, 160, 100, 255],
+            TerrainKind::Breakable => [140, 90, 50, 255],
+            TerrainKind::OneWay => [225, 200, 150, 255],
+        };
+        fill_rect(&mut image, screen(block.pos), block.half_extents, color);
+    }
+    for slope in &level.slopes {
+        let corners: Vec<Vector2> = slope.corners().iter().map(|&c| screen(c).0).collect();
+        fill_convex(&mut image, &corners, [194, 160, 100, 255]);
+    }
+    for volume in &level.volumes {
+        let color = match volume.kind {
+            VolumeKind::Ladder => [150, 100, 40, 200],
+            VolumeKind::Vine => [40, 140, 50, 200],
+            VolumeKind::Water => [40, 110, 220, 120],
+        };
+        fill_rect(&mut image, screen(volume.pos), volume.half_extents, color);
+    }
+    for checkpoint in &level.checkpoints {
+        fill_rect(&mut image, screen(checkpoint.pos), checkpoint.half_extents, [230, 230, 230, 60]);
+    }
+    for coin in &level.coins {
+        fill_rect(&mut image, screen(coin.pos), Vector2::new(8., 8.), [240, 200, 40, 255]);
+    }
+    for enemy in &level.enemies {
+        fill_rect(&mut image, screen(enemy.pos), Vector2::new(16., 16.), [120, 20, 20, 255]);
+    }
+    fill_rect(&mut image, screen(level.spawn), Vector2::new(12., 12.), [255, 255, 255, 255]);
+    fill_rect(&mut image, screen(level.goal), Vector2::new(24., 24.), [60, 200, 90, 255]);
+    image
+}
+
+/// Maps a heat value between 0 and 1 to a colour, from a faint blue through
+/// yellow to an opaque red.
+fn heat_color(heat: f32) -> [u8; 4] {
+    let lerp = |a: f32, b: f32, t: f32| (a + (b - a) * t) as u8;
+    let (r, g, b) = if heat < 0.5 {
+        let t = heat * 2.;
+        (lerp(40., 250., t), lerp(80., 230., t), lerp(255., 40., t))
+    } else {
+        let t = (heat - 0.5) * 2.;
+        (250, lerp(230., 30., t), lerp(40., 20., t))
+    };
+    [r, g, b, (heat.min(1.) * 220.) as u8]
+}
+
+/// Draws a heatmap of `points` over `base`. Each point spreads its heat in
+/// a gaussian falloff of `radius` pixels.
+fn draw_heatmap(base: &RgbaImage, points: &[Vector2], camera: &Camera, radius: f32) -> RgbaImage {
+    let (width, height) = (base.width() as i32, base.height() as i32);
+    let mut heat = vec![0f32; (width * height) as usize];
+    let reach = radius as i32;
+    for &point in points {
+        let p = camera.world_to_screen(WorldPos(point)).0;
+        let (px, py) = (p.x as i32, p.y as i32);
+        for y in (py - reach).max(0)..(py + reach + 1).min(height) {
+            for x in (px - reach).max(0)..(px + reach + 1).min(width) {
+                let d2 = ((x - px) * (x - px) + (y - py) * (y - py)) as f32;
+                if d2 <= radius * radius {
+                    heat[(y * width + x) as usize] += (-d2 / (radius * radius / 4.)).exp();
+                }
+            }
+        }
+    }
+
+    let mut image = base.clone();
+    let max = heat.iter().cloned().fold(0., f32::max);
+    if max > 0. {
+        for y in 0..height {
+            for x in 0..width {
+                let h = heat[(y * width + x) as usize] / max;
+                if h > 0.01 {
+                    blend(&mut image, x, y, heat_color(h));
+                }
+            }
+        }
+    }
+    image
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let contents = fs::read_to_string(&options.level).map_err(|e| format!("{}: {}", options.level.display(), e))?;
+    let level = Level::parse(&contents).map_err(|e| format!("{}: {}", options.level.display(), e))?;
+
+    let mut points = Points::default();
+    for session in &options.sessions {
+        read_session(session, &level.name, &mut points)?;
+    }
+    println!("{}: {} positions, {} jumps, {} deaths from {} sessions",
+        level.name, points.positions.len(), points.jumps.len(), points.deaths.len(), options.sessions.len());
+
+    // Backgrounds are found in the resources directory the level is in
+    let resources = options.level.parent().and_then(|levels| levels.parent()).unwrap_or_else(|| Path::new("."));
+    let camera = Camera::new(WIDTH, HEIGHT);
+    let base = draw_level(&level, resources, options.background, &camera);
+
+    fs::create_dir_all(&options.out).map_err(|e| format!("{}: {}", options.out.display(), e))?;
+    for &(kind, ref kind_points) in &[("positions", &points.positions), ("jumps", &points.jumps), ("deaths", &points.deaths)] {
+        let path = options.out.join(format!("{}-{}.png", level.name, kind));
+        draw_heatmap(&base, kind_points, &camera, options.radius)
+            .save(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run(parse_options()) {
+        eprintln!("heatmap: {}", e);
+        process::exit(1);
+    }
+}
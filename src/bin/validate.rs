@@ -0,0 +1,113 @@
+//! `validate` checks that levels can be finished: that the vending machine
+//! and every coin can be reached from the spawn point, with the tuning
+//! profile and abilities each level is played with. It runs headless:
+//!
+//! ```text
+//! cargo run --bin validate -- [--tuning <profile>] [<level file>...]
+//! ```
+//!
+//! With no level files, every level in `resources/levels` is checked. The
+//! exit status is non-zero if any level can't be finished.
+
+extern crate game;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use game::level::Level;
+use game::tuning::{self, TuningProfiles};
+use game::reachability;
+
+struct Options {
+    levels: Vec<PathBuf>,
+    tuning: Option<String>,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: validate [--tuning <profile>] [<level file>...]");
+    process::exit(2);
+}
+
+fn parse_options() -> Options {
+    let mut levels = Vec::new();
+    let mut tuning = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tuning" => tuning = Some(args.next().unwrap_or_else(|| usage())),
+            "--help" | "-h" => usage(),
+            _ => levels.push(PathBuf::from(arg)),
+        }
+    }
+    Options { levels, tuning }
+}
+
+/// The levels shipped with the game, in `resources/levels`.
+fn shipped_levels() -> Result<Vec<PathBuf>, String> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources").join("levels");
+    let entries = fs::read_dir(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut levels: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().map_or(false, |ext| ext == "lvl"))
+        .collect();
+    levels.sort();
+    Ok(levels)
+}
+
+/// Loads the tuning profiles from the resources directory the level is in,
+/// falling back to the built in profile if there is no tuning file.
+fn load_tuning(level_path: &Path) -> Result<TuningProfiles, String> {
+    let resources = level_path.parent().and_then(|levels| levels.parent()).unwrap_or_else(|| Path::new("."));
+    let path = resources.join(tuning::TUNING_PATH.trim_start_matches('/'));
+    match fs::read_to_string(&path) {
+        Ok(contents) => TuningProfiles::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(_) => Ok(TuningProfiles::builtin()),
+    }
+}
+
+/// Checks one level, printing what can't be reached. Returns whether the
+/// level can be finished.
+fn validate(path: &Path, tuning_override: Option<&str>) -> Result<bool, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let level = Level::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let tuning = load_tuning(path)?;
+    let (profile, stats) = tuning.select(&[tuning_override, level.tuning.as_ref().map(|n| n.as_str())]);
+
+    let reachability = reachability::analyze(&level, &stats);
+    let problems = reachability.problems(&level);
+    if problems.is_empty() {
+        println!("{}: ok ({} tuning)", path.display(), profile);
+    } else {
+        println!("{}: can't be finished ({} tuning)", path.display(), profile);
+        for problem in &problems {
+            println!("    {}", problem);
+        }
+    }
+    let unreachable = reachability.unreachable_terrain();
+    if unreachable > 0 {
+        println!("    note: {} pieces of terrain can't be reached", unreachable);
+    }
+    Ok(problems.is_empty())
+}
+
+fn run(options: Options) -> Result<bool, String> {
+    let levels = if options.levels.is_empty() { shipped_levels()? } else { options.levels };
+    let mut all_ok = true;
+    for path in &levels {
+        all_ok &= validate(path, options.tuning.as_ref().map(|n| n.as_str()))?;
+    }
+    Ok(all_ok)
+}
+
+fn main() {
+    match run(parse_options()) {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("validate: {}", e);
+            process::exit(1);
+        }
+    }
+}
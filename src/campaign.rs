@@ -0,0 +1,219 @@
+//! `campaign` contains the order the levels are played in, grouped into
+//! worlds, and the player's progress through them. The campaign is a plain
+//! text file, `resources/campaign.txt`, with each world followed by its
+//! levels:
+//!
+//! ```text
+//! # comments start with a hash
+//! world The Beach
+//! level /levels/beach.lvl
+//! level /levels/dunes.lvl
+//! world The Pier
+//! level /levels/pier.lvl coins 2000
+//! ```
+//!
+//! The first level is always open, and every other level opens once the
+//! level before it is finished. A level with `coins <n>` also opens early
+//! for a player holding at least that many coins.
+//!
+//! Progress is saved in the ggez user directory: the levels finished, and
+//! the coins held between levels.
+
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use ggez::{Context, GameError, GameResult};
+
+/// Where the campaign is loaded from.
+pub const CAMPAIGN_PATH: &str = "/campaign.txt";
+
+const PROGRESS_PATH: &str = "/progress.txt";
+
+/// A level of the campaign.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CampaignLevel {
+    pub path: String,
+    /// The coins that open the level early, if it can be.
+    pub coins: Option<i32>,
+}
+
+impl CampaignLevel {
+    /// The name shown on the world map, which is the level's file name.
+    pub fn title(&self) -> String {
+        Path::new(&self.path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.path.clone())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct World {
+    pub name: String,
+    pub levels: Vec<CampaignLevel>,
+}
+
+/// `Campaign` is the worlds of the game, in the order they are played.
+#[derive(Clone, Debug)]
+pub struct Campaign {
+    pub worlds: Vec<World>,
+}
+
+impl Campaign {
+    /// A campaign of the one level, for when there is no campaign file.
+    pub fn single(path: &str) -> Campaign {
+        Campaign {
+            worlds: vec![World {
+                name: String::from("Ferris's World"),
+                levels: vec![CampaignLevel { path: path.to_string(), coins: None }],
+            }],
+        }
+    }
+
+    /// Loads the campaign from `CAMPAIGN_PATH`, falling back to a campaign
+    /// of just `start_level` if the file doesn't exist.
+    pub fn load(ctx: &mut Context, start_level: &str) -> GameResult<Campaign> {
+        let mut file = match ctx.filesystem.open(CAMPAIGN_PATH) {
+            Ok(file) => file,
+            Err(_) => return Ok(Campaign::single(start_level)),
+        };
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        Campaign::parse(&contents)
+            .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", CAMPAIGN_PATH, e)))
+    }
+
+    /// Parses the campaign from the text format described in the module docs.
+    pub fn parse(contents: &str) -> Result<Campaign, String> {
+        let mut worlds: Vec<World> = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |msg: &str| format!("line {}: {}", number + 1, msg);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[0] {
+                "world" if fields.len() > 1 => {
+                    worlds.push(World { name: fields[1..].join(" "), levels: Vec::new() });
+                }
+                "level" => {
+                    let world = worlds.last_mut().ok_or_else(|| err("levels must come after a `world` line"))?;
+                    let coins = match fields.len() {
+                        2 => None,
+                        4 if fields[2] == "coins" => {
+                            Some(fields[3].parse::<i32>().map_err(|_| err(&format!("`{}` is not a number", fields[3])))?)
+                        }
+                        _ => return Err(err("expected `level <path> [coins <n>]`")),
+                    };
+                    world.levels.push(CampaignLevel { path: fields[1].to_string(), coins });
+                }
+                "world" => return Err(err("a world needs a name")),
+                other => return Err(err(&format!("unknown entry `{}`", other))),
+            }
+        }
+        if worlds.iter().all(|world| world.levels.is_empty()) {
+            return Err(String::from("the campaign has no levels"));
+        }
+        Ok(Campaign { worlds })
+    }
+
+    /// Every level of the campaign in order, with the world it is in.
+    pub fn levels<'a>(&'a self) -> impl Iterator<Item = (&'a World, &'a CampaignLevel)> + 'a {
+        self.worlds.iter().flat_map(|world| world.levels.iter().map(move |level| (world, level)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels().count()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&CampaignLevel> {
+        self.levels().nth(index).map(|(_, level)| level)
+    }
+
+    /// The index of the level loaded from `path`, if it is in the campaign.
+    pub fn position(&self, path: &str) -> Option<usize> {
+        self.levels().position(|(_, level)| level.path == path)
+    }
+
+    /// Returns true if the level at `index` can be played.
+    pub fn is_unlocked(&self, index: usize, progress: &Progress) -> bool {
+        let level = match self.get(index) {
+            Some(level) => level,
+            None => return false,
+        };
+        index == 0
+            || progress.is_completed(&level.path)
+            || self.get(index - 1).map_or(false, |previous| progress.is_completed(&previous.path))
+            || level.coins.map_or(false, |coins| progress.wallet >= coins)
+    }
+
+    /// What opens a locked level, for the world map: the title of the level
+    /// to finish before it, and the coins that open it early if any do.
+    pub fn unlock_requirement(&self, index: usize) -> Option<(String, Option<i32>)> {
+        let previous = index.checked_sub(1).and_then(|i| self.get(i))?;
+        Some((previous.title(), self.get(index).and_then(|level| level.coins)))
+    }
+}
+
+/// `Progress` is how far the player has got through the campaign.
+#[derive(Clone, Debug, Default)]
+pub struct Progress {
+    completed: BTreeSet<String>,
+    /// The coins held, carried from level to level.
+    pub wallet: i32,
+}
+
+impl Progress {
+    /// Loads the saved progress. A missing or unreadable file is a new game.
+    pub fn load(ctx: &mut Context) -> Progress {
+        let mut contents = String::new();
+        if let Ok(mut file) = ctx.filesystem.open(PROGRESS_PATH) {
+            if file.read_to_string(&mut contents).is_err() {
+                contents.clear();
+            }
+        }
+        Progress::parse(&contents)
+    }
+
+    pub fn save(&self, ctx: &mut Context) -> GameResult<()> {
+        let mut file = ctx.filesystem.create(PROGRESS_PATH)?;
+        file.write_all(self.serialize().as_bytes())?;
+        Ok(())
+    }
+
+    /// Parses the `completed <level path>` and `wallet <coins>` lines.
+    /// Malformed lines are skipped.
+    pub fn parse(contents: &str) -> Progress {
+        let mut progress = Progress::default();
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 2 {
+                continue;
+            }
+            match fields[0] {
+                "completed" => { progress.completed.insert(fields[1].to_string()); }
+                "wallet" => progress.wallet = fields[1].parse().unwrap_or(0),
+                _ => {},
+            }
+        }
+        progress
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = format!("wallet {}\n", self.wallet);
+        for path in &self.completed {
+            out.push_str(&format!("completed {}\n", path));
+        }
+        out
+    }
+
+    pub fn is_completed(&self, path: &str) -> bool {
+        self.completed.contains(path)
+    }
+
+    pub fn complete(&mut self, path: &str) {
+        self.completed.insert(path.to_string());
+    }
+}
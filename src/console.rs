@@ -0,0 +1,210 @@
+//! `console` contains the developer console, a drop-down prompt toggled with
+//! the backtick key for cheat and debug commands while testing. Commands are
+//! parsed into a `Command` for the game state to carry out:
+//!
+//! ```text
+//! teleport <x> <y>
+//! coins <amount>
+//! score <value>
+//! spawn coin <x> <y> [bronze|silver|gold]
+//! spawn enemy <x> <y> [patrol distance]
+//! spawn object <x> <y>
+//! spawn terrain <x> <y> [half width] [half height]
+//! noclip
+//! god
+//! reload
+//! timescale <scale>
+//! dump
+//! help
+//! clear
+//! ```
+
+use ggez::graphics::Vector2;
+
+use actors::coin::CoinKind;
+use actors::types::ActorType;
+
+/// The number of output lines the console keeps.
+const MAX_LINES: usize = 12;
+
+const DEFAULT_PATROL: f32 = 200.;
+const DEFAULT_BLOCK_HALF_EXTENTS: (f32, f32) = (100., 20.);
+
+pub const HELP: &str = "teleport x y | coins n | score n | spawn <coin|enemy|object|terrain> x y .. | \
+noclip | god | reload | timescale s | dump | clear";
+
+/// `Command` is a parsed console command.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Teleport(Vector2),
+    /// Adds coins to the wallet, to spend in the shop.
+    GiveCoins(i32),
+    SetScore(i32),
+    Spawn(Spawn),
+    ToggleNoclip,
+    ToggleGod,
+    ReloadLevel,
+    TimeScale(f64),
+    Dump,
+    Help,
+    Clear,
+}
+
+/// `Spawn` is an actor to add to the running level, by its `ActorType`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Spawn {
+    Coin(Vector2, CoinKind),
+    Enemy(Vector2, f32),
+    /// Moves the vending machine, as a level only has the one.
+    Object(Vector2),
+    /// A block of solid ground, given its center and half extents.
+    Terrain(Vector2, Vector2),
+}
+
+impl Command {
+    /// Parses a command from a line typed into the console.
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let name = match fields.first() {
+            Some(name) => *name,
+            None => return Err(String::from("no command given")),
+        };
+        let args = &fields[1..];
+        match (name, args.len()) {
+            ("teleport", 2) => Ok(Command::Teleport(parse_vector(args[0], args[1])?)),
+            ("coins", 1) => Ok(Command::GiveCoins(parse_number(args[0])?)),
+            ("score", 1) => Ok(Command::SetScore(parse_number(args[0])?)),
+            ("spawn", n) if n >= 3 => parse_spawn(args).map(Command::Spawn),
+            ("noclip", 0) => Ok(Command::ToggleNoclip),
+            ("god", 0) => Ok(Command::ToggleGod),
+            ("reload", 0) => Ok(Command::ReloadLevel),
+            ("timescale", 1) => {
+                let scale = parse_number::<f64>(args[0])?;
+                if scale > 0. {
+                    Ok(Command::TimeScale(scale))
+                } else {
+                    Err(String::from("the time scale must be positive"))
+                }
+            }
+            ("dump", 0) => Ok(Command::Dump),
+            ("help", 0) => Ok(Command::Help),
+            ("clear", 0) => Ok(Command::Clear),
+            ("teleport", _) | ("coins", _) | ("score", _) | ("spawn", _) | ("noclip", _) | ("god", _)
+            | ("reload", _) | ("timescale", _) | ("dump", _) | ("help", _) | ("clear", _) => {
+                Err(format!("wrong arguments for `{}`, try `help`", name))
+            }
+            _ => Err(format!("unknown command `{}`, try `help`", name)),
+        }
+    }
+
+    /// Whether the command changes the run being played. A run that used one
+    /// isn't recorded as a best run or on the leaderboard.
+    pub fn is_cheat(&self) -> bool {
+        match *self {
+            Command::Dump | Command::Help | Command::Clear => false,
+            _ => true,
+        }
+    }
+}
+
+/// Parses `<actor> <x> <y> [..]` for the spawn command.
+fn parse_spawn(args: &[&str]) -> Result<Spawn, String> {
+    let actor = ActorType::from_name(args[0]).ok_or_else(|| format!("unknown actor type `{}`", args[0]))?;
+    let pos = parse_vector(args[1], args[2])?;
+    let extra = &args[3..];
+    match (actor, extra.len()) {
+        (ActorType::Coin, 0) => Ok(Spawn::Coin(pos, CoinKind::Bronze)),
+        (ActorType::Coin, 1) => {
+            let kind = CoinKind::from_name(extra[0]).ok_or_else(|| format!("unknown coin kind `{}`", extra[0]))?;
+            Ok(Spawn::Coin(pos, kind))
+        }
+        (ActorType::Enemy, 0) => Ok(Spawn::Enemy(pos, DEFAULT_PATROL)),
+        (ActorType::Enemy, 1) => Ok(Spawn::Enemy(pos, parse_number(extra[0])?)),
+        (ActorType::Object, 0) => Ok(Spawn::Object(pos)),
+        (ActorType::Terrain, 0) => {
+            let (hw, hh) = DEFAULT_BLOCK_HALF_EXTENTS;
+            Ok(Spawn::Terrain(pos, Vector2::new(hw, hh)))
+        }
+        (ActorType::Terrain, 2) => Ok(Spawn::Terrain(pos, parse_vector(extra[0], extra[1])?)),
+        (ActorType::Player, _) => Err(String::from("there is only one player, use `teleport`")),
+        (ActorType::Zone, _) => Err(String::from("zones can only be added in level files")),
+        _ => Err(format!("wrong arguments for spawning {}", actor.name())),
+    }
+}
+
+fn parse_number<T: ::std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse::<T>().map_err(|_| format!("`{}` is not a valid number", s))
+}
+
+fn parse_vector(x: &str, y: &str) -> Result<Vector2, String> {
+    Ok(Vector2::new(parse_number(x)?, parse_number(y)?))
+}
+
+/// `Console` holds the text being typed and the output of earlier commands.
+pub struct Console {
+    open: bool,
+    input: String,
+    lines: Vec<String>,
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console {
+            open: false,
+            input: String::new(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.input.clear();
+    }
+
+    /// Adds typed text to the input. The backtick that toggles the console
+    /// is left out.
+    pub fn type_text(&mut self, text: &str) {
+        self.input.extend(text.chars().filter(|&c| c != '`' && !c.is_control()));
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// `submit()` echoes the typed line to the output and parses it, leaving
+    /// the input empty. Returns `None` if nothing was typed.
+    pub fn submit(&mut self) -> Option<Result<Command, String>> {
+        let line = self.input.trim().to_string();
+        self.input.clear();
+        if line.is_empty() {
+            return None;
+        }
+        self.print(&format!("> {}", line));
+        Some(Command::parse(&line))
+    }
+
+    /// Adds output to the console, one entry per line of `text`.
+    pub fn print(&mut self, text: &str) {
+        self.lines.extend(text.lines().map(String::from));
+        if self.lines.len() > MAX_LINES {
+            let excess = self.lines.len() - MAX_LINES;
+            self.lines.drain(..excess);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+}
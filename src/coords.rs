@@ -0,0 +1,110 @@
+//! `coords` contains the game's two coordinate systems and the camera that
+//! converts between them.
+//!
+//! Everything in the game lives in world coordinates: actor positions, level
+//! files and the collision world all use world units, with the origin at the
+//! center of the level and Y increasing upwards. Screen coordinates are only
+//! used for drawing, with the origin in the top left of the window and Y
+//! increasing downwards. The `Camera` owns the one transform between them.
+
+use ggez::graphics::{Point2, Vector2};
+
+/// A position in world units, with Y increasing upwards.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WorldPos(pub Vector2);
+
+/// A position in screen pixels, from the top left of the window with Y
+/// increasing downwards.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ScreenPos(pub Vector2);
+
+impl ScreenPos {
+    /// The position as a point to draw at.
+    pub fn point(self) -> Point2 {
+        Point2::new(self.0.x, self.0.y)
+    }
+}
+
+/// `Camera` decides which part of the world is drawn in the window. The world
+/// position at `center` is drawn in the middle of the window.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    width: f32,
+    height: f32,
+    center: Vector2,
+}
+
+impl Camera {
+    /// Creates a camera for a window of the given size, looking at the world origin.
+    pub fn new(width: u32, height: u32) -> Camera {
+        Camera {
+            width: width as f32,
+            height: height as f32,
+            center: Vector2::new(0., 0.),
+        }
+    }
+
+    pub fn center(&self) -> WorldPos {
+        WorldPos(self.center)
+    }
+
+    pub fn look_at(&mut self, center: WorldPos) {
+        self.center = center.0;
+    }
+
+    /// The size of the window in pixels.
+    pub fn size(&self) -> (f32, f32) {
+        (self.width, self.height)
+    }
+
+    /// The world X coordinates of the window's left and right edges.
+    pub fn visible_x(&self) -> (f32, f32) {
+        (self.center.x - self.width / 2., self.center.x + self.width / 2.)
+    }
+
+    pub fn world_to_screen(&self, pos: WorldPos) -> ScreenPos {
+        let relative = pos.0 - self.center;
+        ScreenPos(Vector2::new(relative.x + self.width / 2., self.height / 2. - relative.y))
+    }
+
+    pub fn screen_to_world(&self, pos: ScreenPos) -> WorldPos {
+        let relative = Vector2::new(pos.0.x - self.width / 2., self.height / 2. - pos.0.y);
+        WorldPos(relative + self.center)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn world_origin_is_drawn_in_the_middle() {
+        let camera = Camera::new(1920, 1080);
+        assert_eq!(camera.world_to_screen(WorldPos(Vector2::new(0., 0.))), ScreenPos(Vector2::new(960., 540.)));
+        assert_eq!(camera.visible_x(), (-960., 960.));
+    }
+
+    #[test]
+    fn world_y_up_is_screen_y_down() {
+        let camera = Camera::new(1920, 1080);
+        assert_eq!(camera.world_to_screen(WorldPos(Vector2::new(-960., 540.))), ScreenPos(Vector2::new(0., 0.)));
+        assert_eq!(camera.world_to_screen(WorldPos(Vector2::new(960., -540.))), ScreenPos(Vector2::new(1920., 1080.)));
+        let above = camera.world_to_screen(WorldPos(Vector2::new(0., 100.)));
+        let below = camera.world_to_screen(WorldPos(Vector2::new(0., -100.)));
+        assert!(above.0.y < below.0.y);
+    }
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        let mut camera = Camera::new(1280, 720);
+        camera.look_at(WorldPos(Vector2::new(250., -75.)));
+        assert_eq!(camera.visible_x(), (-390., 890.));
+        for &(x, y) in &[(0., 0.), (-930., 102.5), (1500., -2000.), (250., -75.)] {
+            let world = WorldPos(Vector2::new(x, y));
+            assert_eq!(camera.screen_to_world(camera.world_to_screen(world)), world);
+        }
+        let screen = ScreenPos(Vector2::new(17., 700.));
+        assert_eq!(camera.world_to_screen(camera.screen_to_world(screen)), screen);
+        assert_eq!(camera.world_to_screen(camera.center()), ScreenPos(Vector2::new(640., 360.)));
+    }
+}
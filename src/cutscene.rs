@@ -0,0 +1,474 @@
+//! `cutscene` plays the scripted scenes that tell Ferris's story: dialogue
+//! with speaker portraits and typewriter text, choices that branch the
+//! script, and commands that move actors, wait, play sounds and pan the
+//! camera. Scripts are plain text files in `resources/cutscenes`, one
+//! command per line, run from the top:
+//!
+//! ```text
+//! # comments start with a hash
+//! speaker ferris /player.png Ferris
+//! say ferris Where has all my money gone?
+//! choice chase After him!
+//! choice drink A drink first.
+//! label chase
+//! move player 200 0 1.5
+//! move enemy 0 -100 0 0.5
+//! wait 0.5
+//! sound /jump.wav
+//! pan 300 0 1
+//! goto done
+//! label drink
+//! end
+//! ```
+//!
+//! `speaker <id> <portrait|none> <name>` names someone who can talk, and
+//! `say <id> <text>` shows a line of their dialogue. Choices straight after
+//! a line are offered once it has been read, each jumping to its label.
+//! `move <player|vending|enemy <index>> <dx> <dy> <seconds>` moves an actor
+//! and `pan <dx> <dy> <seconds>` the camera, both relative to where they
+//! are. A script ends at its last line or at `end`. A `goto` may loop back,
+//! as long as the loop shows a line or waits somewhere.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use ggez::graphics::Vector2;
+use ggez::{Context, GameError, GameResult};
+
+/// How many characters of dialogue are typed out per second.
+pub const TYPE_SPEED: f64 = 40.;
+
+/// Someone who can speak in a script.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Speaker {
+    pub name: String,
+    /// The image shown beside their lines, if any.
+    pub portrait: Option<String>,
+}
+
+/// `ActorRef` is an actor of the level a script can move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ActorRef {
+    Player,
+    Vending,
+    Enemy(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Instruction {
+    Say { speaker: String, text: String },
+    /// The choices offered after the line before, as their text and label.
+    Choose(Vec<(String, String)>),
+    Goto(String),
+    Move { actor: ActorRef, offset: Vector2, duration: f64 },
+    Wait(f64),
+    Sound(String),
+    Pan { offset: Vector2, duration: f64 },
+    End,
+}
+
+/// `Script` is a parsed cutscene script.
+#[derive(Clone, Debug)]
+pub struct Script {
+    speakers: BTreeMap<String, Speaker>,
+    instructions: Vec<Instruction>,
+    labels: BTreeMap<String, usize>,
+}
+
+impl Script {
+    pub fn load(ctx: &mut Context, path: &str) -> GameResult<Script> {
+        let mut contents = String::new();
+        let mut file = ctx.filesystem.open(path)?;
+        file.read_to_string(&mut contents)?;
+        Script::parse(&contents)
+            .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path, e)))
+    }
+
+    /// Parses a script from the text format described in the module docs.
+    pub fn parse(contents: &str) -> Result<Script, String> {
+        let mut script = Script {
+            speakers: BTreeMap::new(),
+            instructions: Vec::new(),
+            labels: BTreeMap::new(),
+        };
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let err = |msg: &str| format!("line {}: {}", number + 1, msg);
+            // The text of a line of dialogue or a choice is the rest of the line
+            let rest = |skip: usize| fields[skip..].join(" ");
+            let instruction = match (fields[0], fields.len()) {
+                ("speaker", n) if n >= 4 => {
+                    let portrait = if fields[2] == "none" { None } else { Some(fields[2].to_string()) };
+                    script.speakers.insert(fields[1].to_string(), Speaker { name: rest(3), portrait });
+                    continue;
+                }
+                ("say", n) if n >= 3 => {
+                    if !script.speakers.contains_key(fields[1]) {
+                        return Err(err(&format!("`{}` hasn't been named with `speaker`", fields[1])));
+                    }
+                    Instruction::Say { speaker: fields[1].to_string(), text: rest(2) }
+                }
+                ("choice", n) if n >= 3 => {
+                    let choice = (rest(2), fields[1].to_string());
+                    match script.instructions.last_mut() {
+                        Some(&mut Instruction::Choose(ref mut choices)) => {
+                            choices.push(choice);
+                            continue;
+                        }
+                        Some(&mut Instruction::Say { .. }) => Instruction::Choose(vec![choice]),
+                        _ => return Err(err("choices must follow a `say` line")),
+                    }
+                }
+                ("label", 2) => {
+                    if script.labels.insert(fields[1].to_string(), script.instructions.len()).is_some() {
+                        return Err(err(&format!("label `{}` is defined twice", fields[1])));
+                    }
+                    continue;
+                }
+                ("goto", 2) => Instruction::Goto(fields[1].to_string()),
+                ("move", 5) | ("move", 6) => {
+                    let (actor, args) = match (fields[1], fields.len()) {
+                        ("player", 5) => (ActorRef::Player, &fields[2..]),
+                        ("vending", 5) => (ActorRef::Vending, &fields[2..]),
+                        ("enemy", 6) => (ActorRef::Enemy(parse_number(fields[2]).map_err(|e| err(&e))?), &fields[3..]),
+                        _ => return Err(err("expected `move <player|vending|enemy <index>> <dx> <dy> <seconds>`")),
+                    };
+                    let offset = Vector2::new(parse_number(args[0]).map_err(|e| err(&e))?, parse_number(args[1]).map_err(|e| err(&e))?);
+                    Instruction::Move { actor, offset, duration: parse_duration(args[2]).map_err(|e| err(&e))? }
+                }
+                ("wait", 2) => Instruction::Wait(parse_duration(fields[1]).map_err(|e| err(&e))?),
+                ("sound", 2) => Instruction::Sound(fields[1].to_string()),
+                ("pan", 4) => {
+                    let offset = Vector2::new(parse_number(fields[1]).map_err(|e| err(&e))?, parse_number(fields[2]).map_err(|e| err(&e))?);
+                    Instruction::Pan { offset, duration: parse_duration(fields[3]).map_err(|e| err(&e))? }
+                }
+                ("end", 1) => Instruction::End,
+                _ => return Err(err(&format!("unrecognised command `{}`", line))),
+            };
+            script.instructions.push(instruction);
+        }
+
+        // Every label jumped to has to exist
+        for instruction in &script.instructions {
+            let targets: Vec<&String> = match *instruction {
+                Instruction::Goto(ref label) => vec![label],
+                Instruction::Choose(ref choices) => choices.iter().map(|&(_, ref label)| label).collect(),
+                _ => continue,
+            };
+            if let Some(missing) = targets.into_iter().find(|label| !script.labels.contains_key(label.as_str())) {
+                return Err(format!("label `{}` isn't defined", missing));
+            }
+        }
+
+        // A loop has to stop somewhere, or the cutscene would never give the
+        // game a frame back
+        for (i, instruction) in script.instructions.iter().enumerate() {
+            if let Instruction::Goto(ref label) = *instruction {
+                if script.runs_into(script.labels[label], i) {
+                    return Err(format!("`goto {}` loops forever without a line, wait, move or pan", label));
+                }
+            }
+        }
+        Ok(script)
+    }
+
+    /// Whether running the script from `start` gets to the instruction at
+    /// `target` without stopping to show a line, wait, move or pan.
+    fn runs_into(&self, start: usize, target: usize) -> bool {
+        let mut next = start;
+        let mut visited = vec![false; self.instructions.len()];
+        while let Some(instruction) = self.instructions.get(next) {
+            if next == target {
+                return true;
+            }
+            if visited[next] {
+                return false;
+            }
+            visited[next] = true;
+            next = match *instruction {
+                Instruction::Say { .. } | Instruction::Wait(_) | Instruction::End => return false,
+                Instruction::Move { duration, .. } | Instruction::Pan { duration, .. } if duration > 0. => return false,
+                Instruction::Goto(ref label) => self.labels[label],
+                _ => next + 1,
+            };
+        }
+        false
+    }
+
+    /// The speakers of the script, by their id.
+    pub fn speakers<'a>(&'a self) -> impl Iterator<Item = (&'a str, &'a Speaker)> + 'a {
+        self.speakers.iter().map(|(id, speaker)| (id.as_str(), speaker))
+    }
+}
+
+fn parse_number<T: ::std::str::FromStr>(s: &str) -> Result<T, String> {
+    s.parse::<T>().map_err(|_| format!("`{}` is not a valid number", s))
+}
+
+fn parse_duration(s: &str) -> Result<f64, String> {
+    match parse_number::<f64>(s)? {
+        seconds if seconds >= 0. => Ok(seconds),
+        _ => Err(String::from("times can't be negative")),
+    }
+}
+
+/// `CutsceneEvent` is something the game has to carry out for the cutscene.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CutsceneEvent {
+    /// Moves an actor by the given offset.
+    Move(ActorRef, Vector2),
+    /// Moves the camera by the given offset.
+    Pan(Vector2),
+    PlaySound(String),
+}
+
+/// The line of dialogue being shown, for drawing.
+pub struct Dialogue<'a> {
+    pub speaker_id: &'a str,
+    pub speaker: &'a Speaker,
+    /// The part of the line typed out so far.
+    pub text: &'a str,
+    /// The choices to pick from once the line has been typed out.
+    pub choices: Vec<&'a str>,
+    pub selected: usize,
+}
+
+#[derive(Clone, Debug)]
+enum State {
+    /// Ready to run the next instruction.
+    Running,
+    Talking { speaker: String, text: String, shown: f64, choices: Vec<(String, String)>, selected: usize },
+    Moving { actor: ActorRef, velocity: Vector2, remaining: f64 },
+    Panning { velocity: Vector2, remaining: f64 },
+    Waiting(f64),
+    Finished,
+}
+
+/// `Cutscene` plays a script. The game is paused and its input locked while
+/// a cutscene plays, with the keys going to the dialogue instead.
+pub struct Cutscene {
+    script: Script,
+    next: usize,
+    state: State,
+}
+
+impl Cutscene {
+    pub fn new(script: Script) -> Cutscene {
+        Cutscene { script, next: 0, state: State::Running }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        match self.state {
+            State::Finished => true,
+            _ => false,
+        }
+    }
+
+    /// `update()` plays `dt` seconds of the cutscene, returning what the game
+    /// has to do as a result. Dialogue waits for the player to read it.
+    pub fn update(&mut self, dt: f64) -> Vec<CutsceneEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.state {
+                State::Running => {}
+                State::Talking { ref text, ref mut shown, .. } => {
+                    *shown = (*shown + dt * TYPE_SPEED).min(text.chars().count() as f64);
+                    return events;
+                }
+                State::Moving { actor, velocity, ref mut remaining } => {
+                    let step = remaining.min(dt);
+                    events.push(CutsceneEvent::Move(actor, velocity * step as f32));
+                    *remaining -= step;
+                    if *remaining <= 0. {
+                        self.state = State::Running;
+                    }
+                    return events;
+                }
+                State::Panning { velocity, ref mut remaining } => {
+                    let step = remaining.min(dt);
+                    events.push(CutsceneEvent::Pan(velocity * step as f32));
+                    *remaining -= step;
+                    if *remaining <= 0. {
+                        self.state = State::Running;
+                    }
+                    return events;
+                }
+                State::Waiting(ref mut remaining) => {
+                    *remaining -= dt;
+                    if *remaining <= 0. {
+                        self.state = State::Running;
+                    }
+                    return events;
+                }
+                State::Finished => return events,
+            }
+
+            let instruction = match self.script.instructions.get(self.next) {
+                Some(instruction) => instruction.clone(),
+                None => {
+                    self.state = State::Finished;
+                    return events;
+                }
+            };
+            self.next += 1;
+            match instruction {
+                Instruction::Say { speaker, text } => {
+                    let choices = match self.script.instructions.get(self.next) {
+                        Some(&Instruction::Choose(ref choices)) => choices.clone(),
+                        _ => Vec::new(),
+                    };
+                    if !choices.is_empty() {
+                        self.next += 1;
+                    }
+                    self.state = State::Talking { speaker, text, shown: 0., choices, selected: 0 };
+                }
+                // Choices are taken with the line they follow
+                Instruction::Choose(_) => {}
+                Instruction::Goto(label) => self.next = self.script.labels[&label],
+                Instruction::Move { actor, offset, duration } if duration > 0. => {
+                    self.state = State::Moving { actor, velocity: offset / duration as f32, remaining: duration };
+                }
+                Instruction::Move { actor, offset, .. } => events.push(CutsceneEvent::Move(actor, offset)),
+                Instruction::Pan { offset, duration } if duration > 0. => {
+                    self.state = State::Panning { velocity: offset / duration as f32, remaining: duration };
+                }
+                Instruction::Pan { offset, .. } => events.push(CutsceneEvent::Pan(offset)),
+                Instruction::Wait(seconds) => self.state = State::Waiting(seconds),
+                Instruction::Sound(path) => events.push(CutsceneEvent::PlaySound(path)),
+                Instruction::End => self.state = State::Finished,
+            }
+        }
+    }
+
+    /// `advance()` handles the key to carry on: it finishes typing out the
+    /// line shown, or moves on from it once it has been read, taking the
+    /// selected choice if there are any.
+    pub fn advance(&mut self) {
+        let next = match self.state {
+            State::Talking { ref text, ref mut shown, ref choices, selected, .. } => {
+                let length = text.chars().count() as f64;
+                if *shown < length {
+                    *shown = length;
+                    return;
+                }
+                choices.get(selected).map(|&(_, ref label)| self.script.labels[label])
+            }
+            _ => return,
+        };
+        if let Some(next) = next {
+            self.next = next;
+        }
+        self.state = State::Running;
+    }
+
+    /// Moves the choice selected by `step`, wrapping around.
+    pub fn select(&mut self, step: isize) {
+        if let State::Talking { ref choices, ref mut selected, .. } = self.state {
+            if !choices.is_empty() {
+                let count = choices.len() as isize;
+                *selected = ((*selected as isize + step) % count + count) as usize % choices.len();
+            }
+        }
+    }
+
+    /// The line of dialogue being shown, if any.
+    pub fn dialogue(&self) -> Option<Dialogue> {
+        match self.state {
+            State::Talking { ref speaker, ref text, shown, ref choices, selected } => {
+                let (id, speaker) = self.script.speakers.get_key_value(speaker.as_str())?;
+                let end = text.char_indices().nth(shown as usize).map_or(text.len(), |(i, _)| i);
+                let typed = end == text.len();
+                Some(Dialogue {
+                    speaker_id: id.as_str(),
+                    speaker,
+                    text: &text[..end],
+                    choices: if typed { choices.iter().map(|&(ref text, _)| text.as_str()).collect() } else { Vec::new() },
+                    selected,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEAKER: &str = "speaker ferris none Ferris\n";
+
+    fn script(lines: &str) -> Result<Script, String> {
+        Script::parse(&format!("{}{}", SPEAKER, lines))
+    }
+
+    fn said(cutscene: &Cutscene) -> Option<String> {
+        cutscene.dialogue().map(|dialogue| dialogue.text.to_string())
+    }
+
+    #[test]
+    fn unknown_labels_are_rejected() {
+        assert_eq!(script("goto nowhere\n").unwrap_err(), "label `nowhere` isn't defined");
+        assert_eq!(script("say ferris Hi\nchoice nowhere Go\n").unwrap_err(), "label `nowhere` isn't defined");
+    }
+
+    #[test]
+    fn wrong_field_counts_are_rejected() {
+        for line in &["wait", "wait 1 2", "goto", "pan 1 2", "move player 1 2", "move enemy 1 2 3", "end now", "label", "say ferris"] {
+            let err = script(line).unwrap_err();
+            assert!(err.starts_with("line 2: "), "{}: {}", line, err);
+        }
+    }
+
+    #[test]
+    fn loops_without_waiting_are_rejected() {
+        assert!(script("label top\ngoto top\n").is_err());
+        assert!(script("label a\nsound /jump.wav\nmove player 10 0 0\ngoto b\nlabel b\ngoto a\n").is_err());
+        assert!(script("label top\nwait 0.5\ngoto top\n").is_ok());
+        assert!(script("label top\nsay ferris Again?\ngoto top\n").is_ok());
+        assert!(script("label top\npan 10 0 1\ngoto top\n").is_ok());
+    }
+
+    #[test]
+    fn waits_hold_the_script() {
+        let mut cutscene = Cutscene::new(script("sound /a.wav\nwait 1\nsound /b.wav\n").unwrap());
+        assert_eq!(cutscene.update(0.5), vec![CutsceneEvent::PlaySound(String::from("/a.wav"))]);
+        assert_eq!(cutscene.update(0.25), vec![]);
+        assert_eq!(cutscene.update(0.25), vec![]);
+        assert!(!cutscene.is_finished());
+        assert_eq!(cutscene.update(0.1), vec![CutsceneEvent::PlaySound(String::from("/b.wav"))]);
+        assert!(cutscene.is_finished());
+    }
+
+    #[test]
+    fn goto_skips_to_its_label() {
+        let mut cutscene = Cutscene::new(script("goto last\nsay ferris Skipped\nlabel last\nsay ferris Last\n").unwrap());
+        cutscene.update(0.);
+        cutscene.advance();
+        assert_eq!(said(&cutscene), Some(String::from("Last")));
+        cutscene.advance();
+        cutscene.update(0.);
+        assert!(cutscene.is_finished());
+    }
+
+    #[test]
+    fn choices_jump_to_their_label() {
+        let lines = "say ferris Which way?\nchoice left Left\nchoice right Right\n\
+                     label left\nsay ferris Went left\nend\nlabel right\nsay ferris Went right\n";
+        let mut cutscene = Cutscene::new(script(lines).unwrap());
+        cutscene.update(0.);
+        assert_eq!(cutscene.dialogue().unwrap().choices, Vec::<&str>::new());
+        cutscene.update(10.);
+        assert_eq!(cutscene.dialogue().unwrap().choices, vec!["Left", "Right"]);
+        cutscene.select(-1);
+        assert_eq!(cutscene.dialogue().unwrap().selected, 1);
+        cutscene.advance();
+        cutscene.update(0.);
+        assert_eq!(said(&cutscene), Some(String::from("")));
+        cutscene.advance();
+        assert_eq!(said(&cutscene), Some(String::from("Went right")));
+    }
+}
@@ -0,0 +1,337 @@
+//! `editor` contains the in-game level editor. While editing, the game is
+//! paused and the mouse places, moves and deletes the level's ground blocks,
+//! coins, goal and spawn point, snapped to a grid. Every edit is made to the
+//! `Level` itself, with a snapshot of the level kept for undo and redo, and
+//! the game state rebuilds its collision world from the edited level. An item
+//! being dragged only has its own entity moved, until the drag ends.
+//!
+//! Controls: `1`-`4` pick the tool (ground, coin, goal, spawn), the left
+//! mouse button places with the tool or drags an existing item, and the
+//! right button deletes an item. Ground blocks are placed by dragging out
+//! a rectangle.
+
+use ggez::graphics::Vector2;
+
+use actors::coin::CoinKind;
+use level::{CoinSpawn, GroundBlock, Level, TerrainKind};
+
+/// Size of the grid positions snap to, in world units.
+pub const GRID: f32 = 20.;
+
+/// How close to a coin, the goal or the spawn point a click has to be to
+/// pick it, in world units.
+pub const HANDLE_SIZE: f32 = 30.;
+
+/// The most undo steps kept.
+const MAX_UNDO: usize = 100;
+
+/// `Tool` is what a left click on empty space places.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Tool {
+    Ground,
+    Coin(CoinKind),
+    Goal,
+    Spawn,
+}
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Ground => "ground",
+            Tool::Coin(_) => "coin",
+            Tool::Goal => "goal",
+            Tool::Spawn => "spawn",
+        }
+    }
+}
+
+/// `Item` is a part of the level the editor can pick.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Item {
+    Ground(usize),
+    Coin(usize),
+    Goal,
+    Spawn,
+}
+
+/// What the mouse is doing while its button is held.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Drag {
+    /// Moving an item, keeping the grab point the same distance from its
+    /// position. The undo snapshot is taken once the item first moves.
+    Move { item: Item, offset: Vector2, moved: bool },
+    /// Dragging out a new ground block from the corner `start`.
+    Create { start: Vector2, end: Vector2 },
+}
+
+/// Rounds `pos` to the nearest point on the grid.
+pub fn snap(pos: Vector2) -> Vector2 {
+    Vector2::new((pos.x / GRID).round() * GRID, (pos.y / GRID).round() * GRID)
+}
+
+/// `Editor` holds the editing tool, the drag in progress and the undo history.
+pub struct Editor {
+    tool: Tool,
+    drag: Option<Drag>,
+    undo: Vec<Level>,
+    redo: Vec<Level>,
+    /// The level has edits that haven't been saved.
+    unsaved: bool,
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        Editor {
+            tool: Tool::Ground,
+            drag: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            unsaved: false,
+        }
+    }
+
+    pub fn tool(&self) -> Tool {
+        self.tool
+    }
+
+    pub fn set_tool(&mut self, tool: Tool) {
+        self.tool = tool;
+    }
+
+    pub fn is_unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    pub fn saved(&mut self) {
+        self.unsaved = false;
+    }
+
+    /// The ground block being dragged out, if any, as its center and half extents.
+    pub fn new_block(&self) -> Option<(Vector2, Vector2)> {
+        match self.drag {
+            Some(Drag::Create { start, end }) => Some(block_between(start, end)),
+            _ => None,
+        }
+    }
+
+    /// Remembers `level` as it is before an edit, so the edit can be undone.
+    fn checkpoint(&mut self, level: &Level) {
+        self.undo.push(level.clone());
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.unsaved = true;
+    }
+
+    /// Undoes the latest edit. Returns true if there was one to undo.
+    pub fn undo(&mut self, level: &mut Level) -> bool {
+        match self.undo.pop() {
+            Some(previous) => {
+                self.drag = None;
+                self.redo.push(::std::mem::replace(level, previous));
+                self.unsaved = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redoes the latest undone edit. Returns true if there was one to redo.
+    pub fn redo(&mut self, level: &mut Level) -> bool {
+        match self.redo.pop() {
+            Some(next) => {
+                self.drag = None;
+                self.undo.push(::std::mem::replace(level, next));
+                self.unsaved = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `press()` handles the left mouse button going down at world position
+    /// `pos`. Clicking an item starts moving it; clicking empty space places
+    /// the goal or spawn point there, or a coin, or starts dragging out a
+    /// ground block. Returns true if the level changed.
+    pub fn press(&mut self, level: &mut Level, pos: Vector2) -> bool {
+        if let Some(item) = item_at(level, pos) {
+            self.drag = Some(Drag::Move { item, offset: item_pos(level, item) - pos, moved: false });
+            return false;
+        }
+        let snapped = snap(pos);
+        match self.tool {
+            Tool::Ground => {
+                self.drag = Some(Drag::Create { start: snapped, end: snapped });
+                false
+            }
+            Tool::Coin(kind) => {
+                self.checkpoint(level);
+                level.coins.push(CoinSpawn { pos: snapped, kind });
+                true
+            }
+            Tool::Goal => {
+                self.checkpoint(level);
+                level.goal = snapped;
+                true
+            }
+            Tool::Spawn => {
+                self.checkpoint(level);
+                level.spawn = snapped;
+                true
+            }
+        }
+    }
+
+    /// `motion()` handles the mouse moving to `pos`. Returns the item it
+    /// moved, if the level changed.
+    pub fn motion(&mut self, level: &mut Level, pos: Vector2) -> Option<Item> {
+        match self.drag {
+            Some(Drag::Move { item, offset, moved }) => {
+                let target = snap(pos + offset);
+                if target == item_pos(level, item) {
+                    return None;
+                }
+                if !moved {
+                    self.checkpoint(level);
+                    self.drag = Some(Drag::Move { item, offset, moved: true });
+                }
+                set_item_pos(level, item, target);
+                Some(item)
+            }
+            Some(Drag::Create { start, .. }) => {
+                self.drag = Some(Drag::Create { start, end: snap(pos) });
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// `release()` handles the left mouse button coming back up, finishing
+    /// the drag. Returns true if the level changed.
+    pub fn release(&mut self, level: &mut Level) -> bool {
+        match self.drag.take() {
+            Some(Drag::Create { start, end }) => {
+                let (pos, half_extents) = block_between(start, end);
+                self.checkpoint(level);
+                level.ground.push(GroundBlock { pos, half_extents, kind: TerrainKind::Solid });
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// `delete()` removes the item at `pos`. The goal and spawn point can't
+    /// be deleted, as every level needs them. Returns true if the level changed.
+    pub fn delete(&mut self, level: &mut Level, pos: Vector2) -> bool {
+        let deleted = match item_at(level, pos) {
+            Some(Item::Ground(i)) => {
+                self.checkpoint(level);
+                level.ground.remove(i);
+                true
+            }
+            Some(Item::Coin(i)) => {
+                self.checkpoint(level);
+                level.coins.remove(i);
+                true
+            }
+            _ => false,
+        };
+        // Items after the deleted one have moved down, so a drag can't go on
+        if deleted {
+            self.drag = None;
+        }
+        deleted
+    }
+}
+
+/// The center and half extents of the block with corners `a` and `b`, at
+/// least one grid cell in each direction.
+fn block_between(a: Vector2, b: Vector2) -> (Vector2, Vector2) {
+    let half = Vector2::new(((a.x - b.x).abs() / 2.).max(GRID / 2.), ((a.y - b.y).abs() / 2.).max(GRID / 2.));
+    let center = Vector2::new(a.x.min(b.x) + half.x, a.y.min(b.y) + half.y);
+    (center, half)
+}
+
+/// The item at world position `pos`. Coins, the spawn point and the goal are
+/// picked before ground blocks, which they are often placed on.
+pub fn item_at(level: &Level, pos: Vector2) -> Option<Item> {
+    let near = |p: Vector2| (p.x - pos.x).abs() <= HANDLE_SIZE && (p.y - pos.y).abs() <= HANDLE_SIZE;
+    if let Some(i) = level.coins.iter().rposition(|c| near(c.pos)) {
+        return Some(Item::Coin(i));
+    }
+    if near(level.spawn) {
+        return Some(Item::Spawn);
+    }
+    if near(level.goal) {
+        return Some(Item::Goal);
+    }
+    level.ground.iter()
+        .rposition(|b| (b.pos.x - pos.x).abs() <= b.half_extents.x && (b.pos.y - pos.y).abs() <= b.half_extents.y)
+        .map(Item::Ground)
+}
+
+pub fn item_pos(level: &Level, item: Item) -> Vector2 {
+    match item {
+        Item::Ground(i) => level.ground[i].pos,
+        Item::Coin(i) => level.coins[i].pos,
+        Item::Goal => level.goal,
+        Item::Spawn => level.spawn,
+    }
+}
+
+fn set_item_pos(level: &mut Level, item: Item, pos: Vector2) {
+    match item {
+        Item::Ground(i) => level.ground[i].pos = pos,
+        Item::Coin(i) => level.coins[i].pos = pos,
+        Item::Goal => level.goal = pos,
+        Item::Spawn => level.spawn = pos,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level() -> Level {
+        Level::parse("ground 0 -500 960 32\ncoin 0 0 bronze\ncoin 100 0 gold\ngoal 600 0\n").unwrap()
+    }
+
+    #[test]
+    fn dragging_moves_an_item_on_the_grid() {
+        let mut level = level();
+        let mut editor = Editor::new();
+        editor.press(&mut level, Vector2::new(100., 0.));
+        assert_eq!(editor.motion(&mut level, Vector2::new(147., 52.)), Some(Item::Coin(1)));
+        assert_eq!(level.coins[1].pos, Vector2::new(140., 60.));
+        assert!(!editor.release(&mut level));
+        assert!(editor.undo(&mut level));
+        assert_eq!(level.coins[1].pos, Vector2::new(100., 0.));
+    }
+
+    #[test]
+    fn deleting_the_dragged_item_ends_the_drag() {
+        let mut level = level();
+        let mut editor = Editor::new();
+        editor.press(&mut level, Vector2::new(100., 0.));
+        assert!(editor.delete(&mut level, Vector2::new(100., 0.)));
+        assert_eq!(None, editor.motion(&mut level, Vector2::new(200., 0.)));
+        assert_eq!(level.coins.len(), 1);
+    }
+
+    #[test]
+    fn undo_and_redo_end_the_drag() {
+        let mut level = level();
+        let mut editor = Editor::new();
+        editor.set_tool(Tool::Coin(CoinKind::Silver));
+        editor.press(&mut level, Vector2::new(300., 0.));
+        editor.press(&mut level, Vector2::new(300., 0.));
+        assert!(editor.undo(&mut level));
+        assert_eq!(None, editor.motion(&mut level, Vector2::new(400., 0.)));
+
+        editor.press(&mut level, Vector2::new(100., 0.));
+        assert!(editor.redo(&mut level));
+        assert_eq!(None, editor.motion(&mut level, Vector2::new(400., 0.)));
+        assert_eq!(level.coins.len(), 3);
+    }
+}
@@ -2,21 +2,42 @@
 use ggez::event::{self, Keycode};
 
 /// `Direction` containst he available directions for the player to move.
-/// Currently limited to Left and Right, but can be expanded upon.
+/// Left and Right walk, while Up and Down are used for climbing and swimming.
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
 pub enum Direction {
     Left,
     Right,
+    Up,
+    Down,
 }
 
 /// The functions of `Direction` allow for applying a direction to key
 /// inputs via the `Keycode`, and provide a +/- multiplier to allow for
-/// direction along the X axis.
+/// direction along the X and Y axes.
 impl Direction {
     pub fn movement(self) -> f64 {
         match self {
             Direction::Left => -1.0,
             Direction::Right => 1.0,
+            Direction::Up | Direction::Down => 0.0,
+        }
+    }
+
+    /// The +/- multiplier along the Y axis, with Up being positive as in world coordinates.
+    pub fn vertical(self) -> f64 {
+        match self {
+            Direction::Up => 1.0,
+            Direction::Down => -1.0,
+            Direction::Left | Direction::Right => 0.0,
+        }
+    }
+
+    pub fn opposite(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
         }
     }
 
@@ -27,24 +48,39 @@ impl Direction {
             _ => None,
         }
     }
+
+    fn fromVerticalKey(keycode: Keycode) -> Option<Direction> {
+        match keycode {
+            Keycode::Up => Some(Direction::Up),
+            Keycode::Down => Some(Direction::Down),
+            _ => None,
+        }
+    }
 }
 
 /// `InputEvent` is utlized to generate events for various movement types.
 pub enum InputEvent {
     UpdateMovement(Option<Direction>),
+    /// Up or Down was pressed or released, with the vertical direction still held.
+    UpdateVertical(Option<Direction>),
     PressJump,
+    Dash,
+    GroundPound,
+    DropThrough,
     TimeUpdate,
     Landed,
 }
 
 /// `GameInput` contains a vector of key holds to help ensure constant movement
-/// of the player character while the keys are being pressed.
+/// of the player character while the keys are being pressed. Up and Down are
+/// tracked separately so they can be combined with the other keys.
 pub struct GameInput {
-    held_dirs: Vec<Direction>
+    held_dirs: Vec<Direction>,
+    held_vertical: Vec<Direction>,
 }
 
 impl GameInput {
-    pub fn new() -> GameInput { GameInput {held_dirs: Vec::new() }}
+    pub fn new() -> GameInput { GameInput {held_dirs: Vec::new(), held_vertical: Vec::new() }}
 
     pub fn key_down_event(&mut self, keycode: Keycode) -> Option<InputEvent> {
         if let Some(direction) = Direction::fromKey(keycode) {
@@ -55,6 +91,13 @@ impl GameInput {
             Some(InputEvent::UpdateMovement(self.held_dirs()))
 
         }
+        else if keycode == Keycode::LShift || keycode == Keycode::X {
+            Some(InputEvent::Dash)
+        }
+        else if let Some(direction) = Direction::fromVerticalKey(keycode) {
+            self.held_vertical.push(direction);
+            Some(InputEvent::UpdateVertical(Some(direction)))
+        }
         else {
             None
         }
@@ -65,6 +108,10 @@ impl GameInput {
             self.held_dirs.retain(|&d| d != direction);
             Some(InputEvent::UpdateMovement(self.held_dirs()))
         }
+        else if let Some(direction) = Direction::fromVerticalKey(keycode) {
+            self.held_vertical.retain(|&d| d != direction);
+            Some(InputEvent::UpdateVertical(self.held_vertical()))
+        }
         else{
             None
         }
@@ -73,4 +120,12 @@ impl GameInput {
     pub fn held_dirs(&self) -> Option<Direction> {
         self.held_dirs.last().cloned()
     }
+
+    pub fn held_vertical(&self) -> Option<Direction> {
+        self.held_vertical.last().cloned()
+    }
+
+    pub fn down_held(&self) -> bool {
+        self.held_vertical.contains(&Direction::Down)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,212 @@
+//! `generator` builds playable levels from a seed. A generated level is a
+//! floor and a chain of platforms climbing from the spawn point on the left
+//! to the vending machine on the right, with coins and enemies along the
+//! way. Every platform is placed within the player's jump arc from the one
+//! before it, as worked out from the movement stats, and the finished level
+//! is checked with the reachability analysis before it is returned. The
+//! same seed and stats always produce the same level.
+
+use ggez::graphics::Vector2;
+
+use actors::coin::CoinKind;
+use actors::player::Abilities;
+use actors::status::PlayerStats;
+use level::{CoinSpawn, EnemySpawn, GroundBlock, Level, TerrainKind};
+use reachability::{self, JumpArc};
+
+/// The area of the world a level fits in, which is what the camera shows.
+const LEFT: f32 = -960.;
+const RIGHT: f32 = 960.;
+
+/// The floor runs the width of the level, with its top at `FLOOR_TOP`.
+const FLOOR_TOP: f32 = -468.;
+const FLOOR_HALF_HEIGHT: f32 = 32.;
+
+/// Platforms are kept between these heights, so the vending machine on the
+/// last one stays on the screen.
+const MIN_PLATFORM_TOP: f32 = -400.;
+const MAX_PLATFORM_TOP: f32 = -60.;
+const PLATFORM_HALF_HEIGHT: f32 = 16.;
+const MIN_PLATFORM_HALF_WIDTH: f32 = 70.;
+const MAX_PLATFORM_HALF_WIDTH: f32 = 170.;
+/// The last platform holds the vending machine, so it is always this wide.
+const GOAL_PLATFORM_HALF_WIDTH: f32 = 230.;
+
+/// Fractions of the jump arc used, leaving room for imperfect jumps.
+const MIN_REACH: f32 = 0.35;
+const MAX_REACH: f32 = 0.8;
+const MAX_CLIMB: f32 = 0.75;
+
+/// Offsets from a surface to the positions the level format uses, which are
+/// the top left of the sprites. They match the beach level.
+const SPAWN_HEIGHT: f32 = 170.;
+const ENEMY_HEIGHT: f32 = 100.;
+const ENEMY_WIDTH: f32 = 136.;
+const GOAL_HEIGHT: f32 = 548.;
+/// The goal's position is this far left of the vending machine's center.
+const GOAL_OFFSET: f32 = 250.;
+const COIN_HEIGHT: f32 = 60.;
+
+const ENEMY_CHANCE: f32 = 0.35;
+const FLOOR_ENEMY_CHANCE: f32 = 0.5;
+const COIN_CHANCE: f32 = 0.7;
+const ARC_COIN_CHANCE: f32 = 0.3;
+
+/// How many seeds derived from the given one are tried before giving up.
+const MAX_ATTEMPTS: u64 = 16;
+
+/// `Rng` is a small deterministic random number generator (SplitMix64), so
+/// generated levels don't depend on a platform's random numbers.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number between 0 (inclusive) and 1 (exclusive).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
+
+/// A platform of the chain, as its left and right edges and its top.
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    left: f32,
+    right: f32,
+    top: f32,
+}
+
+impl Span {
+    fn center(&self) -> f32 {
+        (self.left + self.right) / 2.
+    }
+
+    fn half_width(&self) -> f32 {
+        (self.right - self.left) / 2.
+    }
+}
+
+/// `generate()` builds the level for `seed`, for a player with `stats`.
+/// Seeds that can't be laid out are retried with seeds derived from them,
+/// so the same seed always ends with the same level.
+pub fn generate(seed: u64, stats: &PlayerStats) -> Result<Level, String> {
+    let arc = JumpArc::new(stats, Abilities::default());
+    for attempt in 0..MAX_ATTEMPTS {
+        let mut rng = Rng::new(seed.wrapping_add(attempt.wrapping_mul(0x2545_F491_4F6C_DD1D)));
+        if let Some(spans) = lay_out(&mut rng, &arc) {
+            let level = build_level(seed, &spans, &mut rng, &arc);
+            if reachability::analyze(&level, stats).is_solvable() {
+                return Ok(level);
+            }
+        }
+    }
+    Err(format!("couldn't lay out a level for seed {}", seed))
+}
+
+/// Places the chain of platforms from the left of the level to the right.
+/// The first span is the floor around the spawn point, and the last holds
+/// the vending machine.
+fn lay_out(rng: &mut Rng, arc: &JumpArc) -> Option<Vec<Span>> {
+    let mut spans = vec![Span { left: LEFT, right: LEFT + 2. * MIN_PLATFORM_HALF_WIDTH, top: FLOOR_TOP }];
+    let goal_left = RIGHT - 2. * GOAL_PLATFORM_HALF_WIDTH;
+    loop {
+        let from = *spans.last().unwrap();
+        let max_climb = (arc.height() * MAX_CLIMB).min(MAX_PLATFORM_TOP - from.top);
+        let min_top = if from.top == FLOOR_TOP { from.top + arc.height() * 0.3 } else { MIN_PLATFORM_TOP };
+        let top = rng.range(min_top.max(MIN_PLATFORM_TOP), from.top + max_climb.max(0.));
+        let reach = arc.reach(top - from.top)?;
+
+        // Finish with the goal platform once it is within reach
+        if goal_left - from.right <= reach * MAX_REACH {
+            spans.push(Span { left: goal_left.max(from.right), right: RIGHT, top });
+            return Some(spans);
+        }
+        let gap = rng.range(reach * MIN_REACH, reach * MAX_REACH);
+        let left = from.right + gap;
+        let half_width = rng.range(MIN_PLATFORM_HALF_WIDTH, MAX_PLATFORM_HALF_WIDTH);
+        spans.push(Span { left, right: (left + 2. * half_width).min(goal_left), top });
+    }
+}
+
+/// Turns the chain into a level, adding the coins and enemies.
+fn build_level(seed: u64, spans: &[Span], rng: &mut Rng, arc: &JumpArc) -> Level {
+    let mut level = Level {
+        name: format!("generated-{}", seed),
+        background: String::from("/beach.png"),
+        spawn: Vector2::new(LEFT + 20., FLOOR_TOP + SPAWN_HEIGHT),
+        ground: vec![GroundBlock {
+            pos: Vector2::new(0., FLOOR_TOP - FLOOR_HALF_HEIGHT),
+            half_extents: Vector2::new((RIGHT - LEFT) / 2., FLOOR_HALF_HEIGHT),
+            kind: TerrainKind::Solid,
+        }],
+        slopes: Vec::new(),
+        volumes: Vec::new(),
+        checkpoints: Vec::new(),
+        coins: Vec::new(),
+        enemies: Vec::new(),
+        goal: Vector2::new(0., 0.),
+        abilities: Abilities::default(),
+        tuning: None,
+        intro: None,
+        outro: None,
+    };
+
+    let platforms = &spans[1..];
+    for (i, span) in platforms.iter().enumerate() {
+        level.ground.push(GroundBlock {
+            pos: Vector2::new(span.center(), span.top - PLATFORM_HALF_HEIGHT),
+            half_extents: Vector2::new(span.half_width(), PLATFORM_HALF_HEIGHT),
+            kind: TerrainKind::Solid,
+        });
+        let is_goal = i == platforms.len() - 1;
+
+        if !is_goal && rng.chance(COIN_CHANCE) {
+            level.coins.push(CoinSpawn { pos: Vector2::new(span.center(), span.top + COIN_HEIGHT), kind: coin_kind(rng) });
+        }
+        // A gold coin at the top of the jump onto the platform rewards a full jump
+        let from = spans[i];
+        if rng.chance(ARC_COIN_CHANCE) && span.top - from.top < arc.height() - COIN_HEIGHT {
+            let pos = Vector2::new((from.right + span.left) / 2., from.top.max(span.top) + COIN_HEIGHT);
+            level.coins.push(CoinSpawn { pos, kind: CoinKind::Gold });
+        }
+        let room = 2. * span.half_width() - ENEMY_WIDTH;
+        if !is_goal && room > ENEMY_WIDTH && rng.chance(ENEMY_CHANCE) {
+            level.enemies.push(EnemySpawn { pos: Vector2::new(span.left, span.top + ENEMY_HEIGHT), patrol: room });
+        }
+        // Enemies on the floor under the platforms, away from the spawn point
+        if i > 0 && rng.chance(FLOOR_ENEMY_CHANCE) {
+            let patrol = rng.range(100., 300.);
+            level.enemies.push(EnemySpawn { pos: Vector2::new(from.right, FLOOR_TOP + ENEMY_HEIGHT), patrol });
+        }
+    }
+
+    let goal = spans.last().unwrap();
+    level.goal = Vector2::new(goal.center() - GOAL_OFFSET, goal.top + GOAL_HEIGHT);
+    level
+}
+
+fn coin_kind(rng: &mut Rng) -> CoinKind {
+    match rng.next_f32() {
+        r if r < 0.6 => CoinKind::Bronze,
+        r if r < 0.9 => CoinKind::Silver,
+        _ => CoinKind::Gold,
+    }
+}
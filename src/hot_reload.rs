@@ -0,0 +1,106 @@
+//! `hot_reload` watches the resources directory for edited files, so that
+//! levels, sprites, sounds and the movement tuning can be changed while the
+//! game is running. The watcher polls file modification times rather than
+//! relying on platform file events.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use tuning::TUNING_PATH;
+
+/// How often the resources directory is scanned for changes.
+const POLL_INTERVAL_MS: u64 = 500;
+
+/// `Reload` is what needs reloading for a changed resource.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Reload {
+    Level,
+    Image,
+    Sound,
+    Tuning,
+}
+
+impl Reload {
+    /// Decides how to reload the resource at `path`, if it is something the
+    /// game can reload.
+    pub fn classify(path: &str) -> Option<Reload> {
+        if path == TUNING_PATH {
+            return Some(Reload::Tuning);
+        }
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("lvl") => Some(Reload::Level),
+            Some("png") | Some("jpg") => Some(Reload::Image),
+            Some("wav") | Some("ogg") => Some(Reload::Sound),
+            _ => None,
+        }
+    }
+}
+
+/// `ResourceWatcher` remembers the modification time of every file under the
+/// resources directory and reports the files that change.
+pub struct ResourceWatcher {
+    root: PathBuf,
+    modified: HashMap<PathBuf, SystemTime>,
+    last_poll: Instant,
+}
+
+impl ResourceWatcher {
+    pub fn new(root: PathBuf) -> ResourceWatcher {
+        let modified = scan(&root);
+        ResourceWatcher {
+            root,
+            modified,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Returns the resource paths (as used with the ggez filesystem, such as
+    /// `/levels/beach.lvl`) of every file added or modified since the last
+    /// scan. Scans at most once every `POLL_INTERVAL_MS`.
+    pub fn poll(&mut self) -> Vec<String> {
+        if self.last_poll.elapsed() < Duration::from_millis(POLL_INTERVAL_MS) {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let current = scan(&self.root);
+        let mut changed: Vec<String> = current.iter()
+            .filter(|&(path, time)| self.modified.get(path) != Some(time))
+            .filter_map(|(path, _)| self.resource_path(path))
+            .collect();
+        changed.sort();
+        self.modified = current;
+        changed
+    }
+
+    fn resource_path(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        Some(format!("/{}", relative.to_string_lossy().replace('\\', "/")))
+    }
+}
+
+/// Collects the modification time of every file under `dir`. Files that
+/// can't be read are skipped, and picked up again on a later scan.
+fn scan(dir: &Path) -> HashMap<PathBuf, SystemTime> {
+    let mut files = HashMap::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return files,
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir() {
+            files.extend(scan(&path));
+        }
+        else if let Ok(time) = metadata.modified() {
+            files.insert(path, time);
+        }
+    }
+    files
+}
@@ -0,0 +1,180 @@
+//! `layers` contains the named collision layers every collision object
+//! belongs to, and the interaction matrix that decides which layers can
+//! collide with each other. Pairs of layers that don't interact are skipped
+//! by the collision world's broad phase, so they never generate contacts or
+//! proximity events.
+//!
+//! The matrix is read from `resources/collision_layers.txt`, where each line
+//! lists a layer followed by the layers it interacts with:
+//!
+//! ```text
+//! # interactions go both ways
+//! terrain player projectile
+//! player enemy pickup trigger
+//! ```
+
+use std::io::Read;
+
+use ggez::{Context, GameError, GameResult};
+use ncollide::world::CollisionGroups;
+
+/// Where the interaction matrix is loaded from.
+pub const LAYERS_PATH: &str = "/collision_layers.txt";
+
+/// The number of collision layers.
+const LAYER_COUNT: usize = 6;
+
+/// `Layer` is a named collision layer. Each layer is a ncollide collision group.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Layer {
+    Terrain,
+    Player,
+    Enemy,
+    Pickup,
+    Projectile,
+    Trigger,
+}
+
+impl Layer {
+    pub fn all() -> [Layer; LAYER_COUNT] {
+        [Layer::Terrain, Layer::Player, Layer::Enemy, Layer::Pickup, Layer::Projectile, Layer::Trigger]
+    }
+
+    /// The ncollide collision group id of the layer.
+    fn group(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layer::Terrain => "terrain",
+            Layer::Player => "player",
+            Layer::Enemy => "enemy",
+            Layer::Pickup => "pickup",
+            Layer::Projectile => "projectile",
+            Layer::Trigger => "trigger",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Layer> {
+        Layer::all().iter().cloned().find(|layer| layer.name() == name)
+    }
+}
+
+/// `LayerMembership` is how a collision object declares the layers it is on.
+/// By default an object interacts with every layer the matrix allows for its
+/// layers; `whitelist()` narrows that down to only the given layers, and
+/// `blacklist()` removes layers from it.
+#[derive(Clone, Debug)]
+pub struct LayerMembership {
+    layers: Vec<Layer>,
+    whitelist: Option<Vec<Layer>>,
+    blacklist: Vec<Layer>,
+}
+
+impl LayerMembership {
+    pub fn new(layers: &[Layer]) -> LayerMembership {
+        LayerMembership {
+            layers: layers.to_vec(),
+            whitelist: None,
+            blacklist: Vec::new(),
+        }
+    }
+
+    pub fn whitelist(mut self, layers: &[Layer]) -> LayerMembership {
+        self.whitelist = Some(layers.to_vec());
+        self
+    }
+
+    pub fn blacklist(mut self, layers: &[Layer]) -> LayerMembership {
+        self.blacklist = layers.to_vec();
+        self
+    }
+}
+
+/// `InteractionMatrix` records which pairs of layers interact. Interactions
+/// are symmetric.
+#[derive(Clone, Debug)]
+pub struct InteractionMatrix {
+    interacts: [[bool; LAYER_COUNT]; LAYER_COUNT],
+}
+
+impl InteractionMatrix {
+    /// A matrix in which no layers interact.
+    pub fn empty() -> InteractionMatrix {
+        InteractionMatrix { interacts: [[false; LAYER_COUNT]; LAYER_COUNT] }
+    }
+
+    /// Loads the matrix from `LAYERS_PATH`, falling back to the built in
+    /// default if the file doesn't exist.
+    pub fn load(ctx: &mut Context) -> GameResult<InteractionMatrix> {
+        let mut file = match ctx.filesystem.open(LAYERS_PATH) {
+            Ok(file) => file,
+            Err(_) => return Ok(InteractionMatrix::default()),
+        };
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        InteractionMatrix::parse(&contents)
+            .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", LAYERS_PATH, e)))
+    }
+
+    /// Parses the matrix from the text format described in the module docs.
+    pub fn parse(contents: &str) -> Result<InteractionMatrix, String> {
+        let mut matrix = InteractionMatrix::empty();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let layers = line.split_whitespace()
+                .map(|name| Layer::from_name(name)
+                    .ok_or_else(|| format!("line {}: unknown layer `{}`", number + 1, name)))
+                .collect::<Result<Vec<Layer>, String>>()?;
+            for other in &layers[1..] {
+                matrix.set(layers[0], *other, true);
+            }
+        }
+        Ok(matrix)
+    }
+
+    pub fn set(&mut self, a: Layer, b: Layer, interacts: bool) {
+        self.interacts[a.group()][b.group()] = interacts;
+        self.interacts[b.group()][a.group()] = interacts;
+    }
+
+    pub fn interacts(&self, a: Layer, b: Layer) -> bool {
+        self.interacts[a.group()][b.group()]
+    }
+
+    /// `groups()` builds the ncollide collision groups for an object with the
+    /// given membership: a member of its layers, whitelisting every layer one
+    /// of them interacts with, after the membership's own white and blacklist.
+    pub fn groups(&self, membership: &LayerMembership) -> CollisionGroups {
+        let member: Vec<usize> = membership.layers.iter().map(|l| l.group()).collect();
+        let whitelist: Vec<usize> = Layer::all().iter().cloned()
+            .filter(|other| membership.layers.iter().any(|layer| self.interacts(*layer, *other)))
+            .filter(|other| membership.whitelist.as_ref().map_or(true, |w| w.contains(other)))
+            .filter(|other| !membership.blacklist.contains(other))
+            .map(|l| l.group())
+            .collect();
+        CollisionGroups::new()
+            .with_membership(&member)
+            .with_whitelist(&whitelist)
+    }
+}
+
+impl Default for InteractionMatrix {
+    /// The terrain blocks the player and projectiles; enemies meet the player
+    /// and projectiles; pickups and triggers only ever see the player.
+    /// Terrain never interacts with other terrain.
+    fn default() -> InteractionMatrix {
+        let mut matrix = InteractionMatrix::empty();
+        matrix.set(Layer::Terrain, Layer::Player, true);
+        matrix.set(Layer::Terrain, Layer::Projectile, true);
+        matrix.set(Layer::Player, Layer::Enemy, true);
+        matrix.set(Layer::Player, Layer::Pickup, true);
+        matrix.set(Layer::Player, Layer::Trigger, true);
+        matrix.set(Layer::Enemy, Layer::Projectile, true);
+        matrix
+    }
+}
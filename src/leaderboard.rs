@@ -0,0 +1,200 @@
+//! `leaderboard` keeps a local high-score table for every level. Runs are
+//! ranked by score (highest first) and then by completion time (fastest
+//! first). The table is saved as plain text in the ggez user directory so it
+//! survives between sessions.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use ggez::{Context, GameResult};
+
+/// The number of runs kept for each level.
+pub const MAX_ENTRIES: usize = 10;
+
+/// The number of letters a player may enter as their initials.
+pub const INITIALS_LEN: usize = 3;
+
+/// What a run is listed under when the player skips entering initials.
+pub const NO_INITIALS: &str = "---";
+
+const LEADERBOARD_PATH: &str = "/leaderboard.txt";
+
+/// A single completed run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    pub initials: String,
+    pub score: i32,
+    pub time: f64,
+}
+
+impl LeaderboardEntry {
+    pub fn new(initials: &str, score: i32, time: f64) -> LeaderboardEntry {
+        LeaderboardEntry {
+            initials: initials.to_string(),
+            score,
+            time,
+        }
+    }
+
+    /// Returns true if `self` should be ranked above `other`.
+    fn beats(&self, other: &LeaderboardEntry) -> bool {
+        self.score > other.score || (self.score == other.score && self.time < other.time)
+    }
+}
+
+/// `Leaderboard` contains the top `MAX_ENTRIES` runs, keyed by level name.
+pub struct Leaderboard {
+    levels: BTreeMap<String, Vec<LeaderboardEntry>>,
+}
+
+impl Leaderboard {
+    pub fn new() -> Leaderboard {
+        Leaderboard { levels: BTreeMap::new() }
+    }
+
+    /// Loads the leaderboard from the user directory. A missing or unreadable
+    /// file results in an empty leaderboard rather than an error, as there is
+    /// simply no record of any runs yet.
+    pub fn load(ctx: &mut Context) -> Leaderboard {
+        let mut contents = String::new();
+        if let Ok(mut file) = ctx.filesystem.open(LEADERBOARD_PATH) {
+            if file.read_to_string(&mut contents).is_err() {
+                contents.clear();
+            }
+        }
+        Leaderboard::parse(&contents)
+    }
+
+    /// Writes the leaderboard to the user directory.
+    pub fn save(&self, ctx: &mut Context) -> GameResult<()> {
+        let mut file = ctx.filesystem.create(LEADERBOARD_PATH)?;
+        file.write_all(self.serialize().as_bytes())?;
+        Ok(())
+    }
+
+    /// Parses the tab separated `level initials score time` format. Malformed
+    /// lines are skipped.
+    pub fn parse(contents: &str) -> Leaderboard {
+        let mut board = Leaderboard::new();
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 4 {
+                continue;
+            }
+            let score = fields[2].parse::<i32>();
+            let time = fields[3].parse::<f64>();
+            if let (Ok(score), Ok(time)) = (score, time) {
+                board.submit(fields[0], LeaderboardEntry::new(fields[1], score, time));
+            }
+        }
+        board
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        for (level, entries) in &self.levels {
+            for entry in entries {
+                out.push_str(&format!("{}\t{}\t{}\t{:.3}\n", level, entry.initials, entry.score, entry.time));
+            }
+        }
+        out
+    }
+
+    /// Returns true if a run with the given score and time would make it
+    /// onto the table for `level`.
+    pub fn qualifies(&self, level: &str, score: i32, time: f64) -> bool {
+        let candidate = LeaderboardEntry::new("", score, time);
+        match self.levels.get(level) {
+            Some(entries) => entries.len() < MAX_ENTRIES || entries.iter().any(|e| candidate.beats(e)),
+            None => true,
+        }
+    }
+
+    /// Inserts a run into the table for `level`, returning its zero based
+    /// rank, or `None` if it did not make the cut.
+    pub fn submit(&mut self, level: &str, entry: LeaderboardEntry) -> Option<usize> {
+        let entries = self.levels.entry(level.to_string()).or_insert_with(Vec::new);
+        let rank = entries.iter().position(|e| entry.beats(e)).unwrap_or(entries.len());
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+        entries.insert(rank, entry);
+        entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+
+    pub fn entries(&self, level: &str) -> &[LeaderboardEntry] {
+        match self.levels.get(level) {
+            Some(entries) => entries,
+            None => &[],
+        }
+    }
+}
+
+/// Formats a run time in seconds as `m:ss.mmm`.
+pub fn format_time(time: f64) -> String {
+    let minutes = (time / 60.).floor() as u32;
+    let seconds = time - (minutes as f64) * 60.;
+    format!("{}:{:06.3}", minutes, seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_text() {
+        let mut board = Leaderboard::new();
+        board.submit("beach", LeaderboardEntry::new("FER", 300, 42.125));
+        board.submit("beach", LeaderboardEntry::new(NO_INITIALS, 100, 61.5));
+        board.submit("caves", LeaderboardEntry::new("RIS", 50, 9.75));
+        let text = board.serialize();
+        assert_eq!(text, "beach\tFER\t300\t42.125\nbeach\t---\t100\t61.500\ncaves\tRIS\t50\t9.750\n");
+        assert_eq!(Leaderboard::parse(&text).serialize(), text);
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let board = Leaderboard::parse("beach\tFER\t300\t42.125\nbeach\tFER\tlots\t1.0\nbeach\tFER\t300\ngarbage\n");
+        assert_eq!(board.entries("beach"), &[LeaderboardEntry::new("FER", 300, 42.125)][..]);
+        assert!(board.entries("caves").is_empty());
+    }
+
+    #[test]
+    fn higher_scores_then_faster_times_rank_first() {
+        let mut board = Leaderboard::new();
+        assert_eq!(board.submit("beach", LeaderboardEntry::new("AAA", 100, 30.)), Some(0));
+        assert_eq!(board.submit("beach", LeaderboardEntry::new("BBB", 200, 50.)), Some(0));
+        assert_eq!(board.submit("beach", LeaderboardEntry::new("CCC", 100, 20.)), Some(1));
+        // A tie on score and time goes below the run already there
+        assert_eq!(board.submit("beach", LeaderboardEntry::new("DDD", 100, 20.)), Some(2));
+        let initials: Vec<&str> = board.entries("beach").iter().map(|e| e.initials.as_str()).collect();
+        assert_eq!(initials, vec!["BBB", "CCC", "DDD", "AAA"]);
+    }
+
+    #[test]
+    fn only_the_top_runs_are_kept() {
+        let mut board = Leaderboard::new();
+        for score in 1..MAX_ENTRIES as i32 + 1 {
+            assert!(board.qualifies("beach", score, 10.));
+            board.submit("beach", LeaderboardEntry::new("AAA", score * 10, 10.));
+        }
+        assert_eq!(board.entries("beach").len(), MAX_ENTRIES);
+        assert!(!board.qualifies("beach", 10, 10.));
+        assert!(!board.qualifies("beach", 10, 11.));
+        assert_eq!(board.submit("beach", LeaderboardEntry::new("BBB", 10, 10.)), None);
+        assert!(board.qualifies("beach", 10, 9.));
+        assert_eq!(board.submit("beach", LeaderboardEntry::new("CCC", 55, 10.)), Some(5));
+        assert_eq!(board.entries("beach").len(), MAX_ENTRIES);
+        assert_eq!(board.entries("beach").last().unwrap().score, 20);
+        assert!(board.qualifies("caves", 0, 999.));
+    }
+
+    #[test]
+    fn times_are_formatted_as_minutes_and_seconds() {
+        assert_eq!(format_time(0.), "0:00.000");
+        assert_eq!(format_time(9.5), "0:09.500");
+        assert_eq!(format_time(75.25), "1:15.250");
+        assert_eq!(format_time(600.), "10:00.000");
+    }
+}
@@ -0,0 +1,329 @@
+//! `Level` describes the layout of a single level: the background, where
+//! Ferris starts, the ground, the coins and the vending machine goal. Levels
+//! are plain text files in `resources/levels`, one item per line, with all
+//! positions given in world coordinates (origin at the center of the screen,
+//! Y increasing upwards):
+//!
+//! ```text
+//! # comments start with a hash
+//! name beach
+//! background /beach.png
+//! spawn -955 0
+//! ground <x> <y> <half width> <half height>
+//! breakable <x> <y> <half width> <half height>
+//! platform <x> <y> <half width>
+//! slope <start x> <start y> <end x> <end y>
+//! ladder <x> <y> <half width> <half height>
+//! vine <x> <y> <half width> <half height>
+//! water <x> <y> <half width> <half height>
+//! checkpoint <x> <y> <half width> <half height>
+//! coin <x> <y> <bronze|silver|gold>
+//! enemy <x> <y> <patrol distance>
+//! goal <x> <y>
+//! abilities [double_jump] [wall_jump]
+//! tuning <profile>
+//! intro <cutscene script>
+//! outro <cutscene script>
+//! ```
+
+use std::io::Read;
+
+use ggez::graphics::Vector2;
+use ggez::{Context, GameError, GameResult};
+
+use actors::coin::CoinKind;
+use actors::player::Abilities;
+
+/// Half height of a one-way platform.
+pub const PLATFORM_HALF_HEIGHT: f32 = 8.;
+
+/// How far the solid body of a slope extends below its lowest end.
+pub const SLOPE_THICKNESS: f32 = 64.;
+
+/// `TerrainKind` decides how the player collides with a piece of terrain.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum TerrainKind {
+    Solid,
+    /// Solid, but smashed by a ground pound.
+    Breakable,
+    /// Only solid from above; can be jumped through from below and dropped
+    /// through with Down + Jump.
+    OneWay,
+}
+
+/// An axis aligned block of ground. `pos` is the center of the block.
+#[derive(Clone, Copy, Debug)]
+pub struct GroundBlock {
+    pub pos: Vector2,
+    pub half_extents: Vector2,
+    pub kind: TerrainKind,
+}
+
+/// A solid ramp whose walkable surface runs from `start` to `end`.
+#[derive(Clone, Copy, Debug)]
+pub struct Slope {
+    pub start: Vector2,
+    pub end: Vector2,
+}
+
+impl Slope {
+    /// The corners of the ramp's solid body: the surface, and a flat bottom
+    /// `SLOPE_THICKNESS` below its lowest end.
+    pub fn corners(&self) -> [Vector2; 4] {
+        let bottom = self.start.y.min(self.end.y) - SLOPE_THICKNESS;
+        [
+            self.start,
+            self.end,
+            Vector2::new(self.end.x, bottom),
+            Vector2::new(self.start.x, bottom),
+        ]
+    }
+}
+
+/// `VolumeKind` is the kind of a non-solid area of the level that changes how
+/// the player moves while inside it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum VolumeKind {
+    /// Climbed quickly straight up and down.
+    Ladder,
+    /// Climbed more slowly, but can be clambered along sideways.
+    Vine,
+    /// Swum through, with buoyancy and drag.
+    Water,
+}
+
+impl VolumeKind {
+    pub fn is_climbable(self) -> bool {
+        self != VolumeKind::Water
+    }
+}
+
+/// An axis aligned volume the player can move through. `pos` is the center of the volume.
+#[derive(Clone, Copy, Debug)]
+pub struct Volume {
+    pub pos: Vector2,
+    pub half_extents: Vector2,
+    pub kind: VolumeKind,
+}
+
+/// A zone that becomes the player's respawn point once passed through.
+#[derive(Clone, Copy, Debug)]
+pub struct Checkpoint {
+    pub pos: Vector2,
+    pub half_extents: Vector2,
+}
+
+impl Checkpoint {
+    /// Where the player respawns: the top left corner of the zone, as the
+    /// player is positioned by the top left of its sprite.
+    pub fn respawn(&self) -> Vector2 {
+        Vector2::new(self.pos.x - self.half_extents.x, self.pos.y + self.half_extents.y)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CoinSpawn {
+    pub pos: Vector2,
+    pub kind: CoinKind,
+}
+
+/// An enemy that patrols `patrol` units to either side of `pos`.
+#[derive(Clone, Copy, Debug)]
+pub struct EnemySpawn {
+    pub pos: Vector2,
+    pub patrol: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub name: String,
+    pub background: String,
+    pub spawn: Vector2,
+    pub ground: Vec<GroundBlock>,
+    pub slopes: Vec<Slope>,
+    pub volumes: Vec<Volume>,
+    pub checkpoints: Vec<Checkpoint>,
+    pub coins: Vec<CoinSpawn>,
+    pub enemies: Vec<EnemySpawn>,
+    pub goal: Vector2,
+    pub abilities: Abilities,
+    /// The movement tuning profile to play the level with, if not the default.
+    pub tuning: Option<String>,
+    /// The cutscenes played before the level starts and once it is finished.
+    pub intro: Option<String>,
+    pub outro: Option<String>,
+}
+
+impl Level {
+    /// Loads and parses the level file at `path` through the ggez filesystem.
+    pub fn load(ctx: &mut Context, path: &str) -> GameResult<Level> {
+        let mut contents = String::new();
+        let mut file = ctx.filesystem.open(path)?;
+        file.read_to_string(&mut contents)?;
+        Level::parse(&contents)
+            .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path, e)))
+    }
+
+    /// Parses a level from the text format described in the module docs.
+    pub fn parse(contents: &str) -> Result<Level, String> {
+        let mut level = Level {
+            name: String::from("untitled"),
+            background: String::from("/beach.png"),
+            spawn: Vector2::new(0., 0.),
+            ground: Vec::new(),
+            slopes: Vec::new(),
+            volumes: Vec::new(),
+            checkpoints: Vec::new(),
+            coins: Vec::new(),
+            enemies: Vec::new(),
+            goal: Vector2::new(0., 0.),
+            abilities: Abilities::default(),
+            tuning: None,
+            intro: None,
+            outro: None,
+        };
+        let mut has_goal = false;
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let err = |msg: &str| format!("line {}: {}", number + 1, msg);
+            match fields[0] {
+                "name" if fields.len() == 2 => level.name = fields[1].to_string(),
+                "background" if fields.len() == 2 => level.background = fields[1].to_string(),
+                "tuning" if fields.len() == 2 => level.tuning = Some(fields[1].to_string()),
+                "intro" if fields.len() == 2 => level.intro = Some(fields[1].to_string()),
+                "outro" if fields.len() == 2 => level.outro = Some(fields[1].to_string()),
+                "spawn" if fields.len() == 3 => level.spawn = parse_vector(fields[1], fields[2]).map_err(|e| err(&e))?,
+                "goal" if fields.len() == 3 => {
+                    level.goal = parse_vector(fields[1], fields[2]).map_err(|e| err(&e))?;
+                    has_goal = true;
+                }
+                "ground" | "breakable" if fields.len() == 5 => level.ground.push(GroundBlock {
+                    pos: parse_vector(fields[1], fields[2]).map_err(|e| err(&e))?,
+                    half_extents: parse_vector(fields[3], fields[4]).map_err(|e| err(&e))?,
+                    kind: if fields[0] == "breakable" { TerrainKind::Breakable } else { TerrainKind::Solid },
+                }),
+                "platform" if fields.len() == 4 => level.ground.push(GroundBlock {
+                    pos: parse_vector(fields[1], fields[2]).map_err(|e| err(&e))?,
+                    half_extents: Vector2::new(
+                        fields[3].parse::<f32>().map_err(|_| err("half width is not a number"))?,
+                        PLATFORM_HALF_HEIGHT,
+                    ),
+                    kind: TerrainKind::OneWay,
+                }),
+                "slope" if fields.len() == 5 => {
+                    let start = parse_vector(fields[1], fields[2]).map_err(|e| err(&e))?;
+                    let end = parse_vector(fields[3], fields[4]).map_err(|e| err(&e))?;
+                    if start.x == end.x {
+                        return Err(err("a slope can't be vertical"));
+                    }
+                    level.slopes.push(Slope { start, end });
+                }
+                "ladder" | "vine" | "water" if fields.len() == 5 => level.volumes.push(Volume {
+                    pos: parse_vector(fields[1], fields[2]).map_err(|e| err(&e))?,
+                    half_extents: parse_vector(fields[3], fields[4]).map_err(|e| err(&e))?,
+                    kind: match fields[0] {
+                        "ladder" => VolumeKind::Ladder,
+                        "vine" => VolumeKind::Vine,
+                        _ => VolumeKind::Water,
+                    },
+                }),
+                "checkpoint" if fields.len() == 5 => level.checkpoints.push(Checkpoint {
+                    pos: parse_vector(fields[1], fields[2]).map_err(|e| err(&e))?,
+                    half_extents: parse_vector(fields[3], fields[4]).map_err(|e| err(&e))?,
+                }),
+                "enemy" if fields.len() == 4 => level.enemies.push(EnemySpawn {
+                    pos: parse_vector(fields[1], fields[2]).map_err(|e| err(&e))?,
+                    patrol: fields[3].parse::<f32>().map_err(|_| err("patrol distance is not a number"))?,
+                }),
+                "coin" if fields.len() == 4 => level.coins.push(CoinSpawn {
+                    pos: parse_vector(fields[1], fields[2]).map_err(|e| err(&e))?,
+                    kind: CoinKind::from_name(fields[3]).ok_or_else(|| err("unknown coin kind"))?,
+                }),
+                "abilities" => {
+                    for ability in &fields[1..] {
+                        match *ability {
+                            "double_jump" => level.abilities.double_jump = true,
+                            "wall_jump" => level.abilities.wall_jump = true,
+                            _ => return Err(err(&format!("unknown ability `{}`", ability))),
+                        }
+                    }
+                }
+                _ => return Err(err(&format!("unrecognised entry `{}`", line))),
+            }
+        }
+
+        if !has_goal {
+            return Err(String::from("level has no goal"));
+        }
+        Ok(level)
+    }
+
+    /// `serialize()` writes the level back out in the text format described in
+    /// the module docs. Comments and the order of entries in the original file
+    /// aren't kept.
+    pub fn serialize(&self) -> String {
+        let mut out = format!("name {}\nbackground {}\n", self.name, self.background);
+        if let Some(ref tuning) = self.tuning {
+            out += &format!("tuning {}\n", tuning);
+        }
+        if let Some(ref intro) = self.intro {
+            out += &format!("intro {}\n", intro);
+        }
+        if let Some(ref outro) = self.outro {
+            out += &format!("outro {}\n", outro);
+        }
+        out += &format!("spawn {} {}\n\n", self.spawn.x, self.spawn.y);
+        for block in &self.ground {
+            let (x, y, hw, hh) = (block.pos.x, block.pos.y, block.half_extents.x, block.half_extents.y);
+            out += &match block.kind {
+                TerrainKind::Solid => format!("ground {} {} {} {}\n", x, y, hw, hh),
+                TerrainKind::Breakable => format!("breakable {} {} {} {}\n", x, y, hw, hh),
+                TerrainKind::OneWay => format!("platform {} {} {}\n", x, y, hw),
+            };
+        }
+        for slope in &self.slopes {
+            out += &format!("slope {} {} {} {}\n", slope.start.x, slope.start.y, slope.end.x, slope.end.y);
+        }
+        for volume in &self.volumes {
+            let name = match volume.kind {
+                VolumeKind::Ladder => "ladder",
+                VolumeKind::Vine => "vine",
+                VolumeKind::Water => "water",
+            };
+            out += &format!("{} {} {} {} {}\n", name, volume.pos.x, volume.pos.y, volume.half_extents.x, volume.half_extents.y);
+        }
+        for checkpoint in &self.checkpoints {
+            out += &format!("checkpoint {} {} {} {}\n", checkpoint.pos.x, checkpoint.pos.y, checkpoint.half_extents.x, checkpoint.half_extents.y);
+        }
+        out += "\n";
+        for coin in &self.coins {
+            out += &format!("coin {} {} {}\n", coin.pos.x, coin.pos.y, coin.kind.name());
+        }
+        for enemy in &self.enemies {
+            out += &format!("enemy {} {} {}\n", enemy.pos.x, enemy.pos.y, enemy.patrol);
+        }
+        out += &format!("\ngoal {} {}\n", self.goal.x, self.goal.y);
+        let mut abilities = Vec::new();
+        if self.abilities.double_jump {
+            abilities.push("double_jump");
+        }
+        if self.abilities.wall_jump {
+            abilities.push("wall_jump");
+        }
+        if !abilities.is_empty() {
+            out += &format!("abilities {}\n", abilities.join(" "));
+        }
+        out
+    }
+}
+
+fn parse_vector(x: &str, y: &str) -> Result<Vector2, String> {
+    let x = x.parse::<f32>().map_err(|_| format!("`{}` is not a number", x))?;
+    let y = y.parse::<f32>().map_err(|_| format!("`{}` is not a number", y))?;
+    Ok(Vector2::new(x, y))
+}
@@ -0,0 +1,31 @@
+//! The modules of Ferris's game: the actors, levels, menus and everything
+//! else the game loop in `main.rs` is built from. They are a library so the
+//! level tools in `src/bin` and the tests can use them too.
+
+extern crate ggez;
+#[macro_use]
+extern crate log;
+extern crate ncollide;
+
+pub mod actors;
+pub mod campaign;
+pub mod console;
+pub mod coords;
+pub mod cutscene;
+pub mod editor;
+pub mod game_inputs;
+pub mod generator;
+pub mod hot_reload;
+pub mod layers;
+pub mod leaderboard;
+pub mod level;
+pub mod locale;
+pub mod logging;
+pub mod reachability;
+pub mod settings;
+pub mod shop;
+pub mod speedrun;
+pub mod telemetry;
+pub mod triggers;
+pub mod tuning;
+pub mod ui;
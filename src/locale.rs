@@ -0,0 +1,238 @@
+//! `locale` holds the text shown in the game in each language it has been
+//! translated to. Every language has a string table in `resources/locales`,
+//! named after its language code, with one `key = text` entry per line:
+//!
+//! ```text
+//! # comments start with a hash
+//! language.name = Deutsch
+//! language.fonts = /prstartk.ttf /DejaVuSerif.ttf
+//! hud.score = Punkte: {score}
+//! win.next = Weiter mit {level}.\nEscape beendet das Spiel.
+//! ```
+//!
+//! `{name}` is replaced with the value given for it, and `\n` starts a new
+//! line. Entries missing from a table are taken from the English table.
+//!
+//! `language.fonts` lists the fonts the language can be drawn with, best
+//! first. The game uses the first of them that it can load, and the pixel
+//! font if none can be, so a script the pixel font has no glyphs for can
+//! list a font that does. All text is drawn with that one font, as ggez
+//! doesn't fall back to another font for glyphs it is missing, so a
+//! language is only offered if the first font it lists is in the resources.
+//! Cutscene scripts are translated by putting the
+//! translation in a directory named after the language code, next to the
+//! original: `/cutscenes/de/beach_intro.txt` for `/cutscenes/beach_intro.txt`.
+//!
+//! The language chosen in the game is saved in the ggez user directory.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use ggez::{Context, GameError, GameResult};
+
+/// The language the game is written in, which every other language falls
+/// back to.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// The font used when none of a language's fonts can be loaded.
+pub const DEFAULT_FONT: &str = "/prstartk.ttf";
+
+const LOCALES_DIR: &str = "/locales";
+
+const LANGUAGE_PATH: &str = "/language.txt";
+
+/// `StringTable` is the text of one language, by key.
+#[derive(Clone, Debug, Default)]
+pub struct StringTable {
+    entries: BTreeMap<String, String>,
+}
+
+impl StringTable {
+    /// Parses a string table from the text format described in the module docs.
+    pub fn parse(contents: &str) -> Result<StringTable, String> {
+        let mut entries = BTreeMap::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |msg: &str| format!("line {}: {}", number + 1, msg);
+            let split = line.find('=').ok_or_else(|| err("expected `key = text`"))?;
+            let key = line[..split].trim();
+            if key.is_empty() {
+                return Err(err("the entry has no key"));
+            }
+            let text = line[split + 1..].trim().replace("\\n", "\n");
+            if entries.insert(key.to_string(), text).is_some() {
+                return Err(err(&format!("`{}` is defined twice", key)));
+            }
+        }
+        Ok(StringTable { entries })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries.get(key).map(|text| text.as_str())
+    }
+}
+
+fn table_path(code: &str) -> String {
+    format!("{}/{}.txt", LOCALES_DIR, code)
+}
+
+fn load_table(ctx: &mut Context, code: &str) -> GameResult<StringTable> {
+    let path = table_path(code);
+    let mut contents = String::new();
+    let mut file = ctx.filesystem.open(&path)?;
+    file.read_to_string(&mut contents)?;
+    StringTable::parse(&contents)
+        .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path, e)))
+}
+
+/// `Locale` is the language the game is being played in.
+#[derive(Clone, Debug)]
+pub struct Locale {
+    code: String,
+    table: StringTable,
+    fallback: StringTable,
+}
+
+impl Locale {
+    /// Loads the string table for the language `code`, along with the
+    /// English table its missing entries are taken from.
+    pub fn load(ctx: &mut Context, code: &str) -> GameResult<Locale> {
+        let fallback = load_table(ctx, DEFAULT_LANGUAGE)?;
+        let table = if code == DEFAULT_LANGUAGE { fallback.clone() } else { load_table(ctx, code)? };
+        Ok(Locale { code: code.to_string(), table, fallback })
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// The name of the language, in the language itself.
+    pub fn name(&self) -> String {
+        self.text("language.name")
+    }
+
+    /// The text for `key`, or the key itself if no table has it.
+    pub fn text(&self, key: &str) -> String {
+        self.get(key).unwrap_or(key).to_string()
+    }
+
+    /// The text for `key`, or `default` if no table has it. This is for text
+    /// that comes from the game's data files, such as world names.
+    pub fn text_or(&self, key: &str, default: &str) -> String {
+        self.get(key).unwrap_or(default).to_string()
+    }
+
+    /// The text for `key` with each `{name}` replaced by its value in `args`.
+    pub fn format(&self, key: &str, args: &[(&str, String)]) -> String {
+        args.iter().fold(self.text(key), |text, &(name, ref value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.table.get(key).or_else(|| self.fallback.get(key))
+    }
+
+    /// The fonts to try for the language, best first, ending with the
+    /// pixel font.
+    pub fn fonts(&self) -> Vec<String> {
+        let mut fonts: Vec<String> = self.table.get("language.fonts")
+            .map(|fonts| fonts.split_whitespace().map(|font| font.to_string()).collect())
+            .unwrap_or_default();
+        if !fonts.iter().any(|font| font == DEFAULT_FONT) {
+            fonts.push(DEFAULT_FONT.to_string());
+        }
+        fonts
+    }
+
+    /// The translation of the resource at `path` for the language, if there
+    /// is one, or else `path` itself.
+    pub fn localized_path(&self, ctx: &mut Context, path: &str) -> String {
+        let original = Path::new(path);
+        let translated = match (original.parent(), original.file_name()) {
+            (Some(dir), Some(name)) => dir.join(&self.code).join(name).to_string_lossy().into_owned(),
+            _ => return path.to_string(),
+        };
+        if self.code != DEFAULT_LANGUAGE && ctx.filesystem.is_file(&translated) {
+            translated
+        } else {
+            path.to_string()
+        }
+    }
+}
+
+/// The codes of the languages with a string table and their first font,
+/// in alphabetical order.
+pub fn languages(ctx: &mut Context) -> Vec<String> {
+    let mut codes: Vec<String> = match ctx.filesystem.read_dir(LOCALES_DIR) {
+        Ok(paths) => paths.into_iter()
+            .filter(|path| path.extension().map_or(false, |ext| ext == "txt"))
+            .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    codes.retain(|code| code == DEFAULT_LANGUAGE || has_font(ctx, code));
+    if !codes.iter().any(|code| code == DEFAULT_LANGUAGE) {
+        codes.push(DEFAULT_LANGUAGE.to_string());
+    }
+    codes.sort();
+    codes
+}
+
+/// Whether the first font listed for the language `code` is in the
+/// resources, or it lists none and is drawn with the pixel font.
+fn has_font(ctx: &mut Context, code: &str) -> bool {
+    let table = match load_table(ctx, code) {
+        Ok(table) => table,
+        Err(e) => {
+            warn!("Unable to load the {} strings: {}", code, e);
+            return false;
+        }
+    };
+    match table.get("language.fonts").and_then(|fonts| fonts.split_whitespace().next()) {
+        Some(font) if !ctx.filesystem.is_file(font) => {
+            info!("{} isn't offered, as its font {} isn't in the resources", code, font);
+            false
+        }
+        _ => true,
+    }
+}
+
+/// The language to start the game in: the one last chosen in the game, or
+/// else the system's language if there is a table for it, or else English.
+pub fn initial_language(ctx: &mut Context, available: &[String]) -> String {
+    let mut saved = String::new();
+    if let Ok(mut file) = ctx.filesystem.open(LANGUAGE_PATH) {
+        if file.read_to_string(&mut saved).is_err() {
+            saved.clear();
+        }
+    }
+    let saved = saved.trim().to_string();
+    let system = system_language();
+    [Some(saved), system].iter()
+        .filter_map(|code| code.as_ref())
+        .find(|code| available.contains(code))
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_LANGUAGE.to_string())
+}
+
+/// Saves the language chosen in the game, to start in next time.
+pub fn save_language(ctx: &mut Context, code: &str) -> GameResult<()> {
+    let mut file = ctx.filesystem.create(LANGUAGE_PATH)?;
+    file.write_all(code.as_bytes())?;
+    Ok(())
+}
+
+/// The language code of the system's locale, such as `de` for `de_DE.UTF-8`.
+fn system_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .map(|value| value.split(|c| c == '_' || c == '.' || c == '-').next().unwrap_or("").to_lowercase())
+        .filter(|code| !code.is_empty() && code != "c" && code != "posix")
+}
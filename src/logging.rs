@@ -0,0 +1,97 @@
+//! `logging` is the game's logger for the `log` macros. Messages are written
+//! to stderr with their level and module, and can be filtered per module
+//! with a spec given by `--log <spec>` or the `FERRIS_LOG` environment
+//! variable. A spec is a comma separated list of a default level and
+//! `module=level` overrides, for example:
+//!
+//! ```text
+//! warn,hot_reload=debug,actors::player=trace
+//! ```
+//!
+//! Module names are relative to the game, so `triggers` is the game's
+//! `triggers` module, while other crates are given in full, such as `ggez`.
+
+use log::{self, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// The spec used when none is given.
+pub const DEFAULT_SPEC: &str = "info";
+
+/// The environment variable the spec is read from.
+pub const LOG_ENV: &str = "FERRIS_LOG";
+
+/// The game's own modules log under this crate name.
+const CRATE_NAME: &str = "game";
+
+/// `Logger` writes every message at or above the level for its module.
+pub struct Logger {
+    default: LevelFilter,
+    /// Module overrides, most specific first.
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl Logger {
+    /// Parses the filter spec described in the module docs.
+    pub fn parse(spec: &str) -> Result<Logger, String> {
+        let mut logger = Logger {
+            default: LevelFilter::Info,
+            modules: Vec::new(),
+        };
+        for part in spec.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let mut fields = part.splitn(2, '=');
+            let first = fields.next().unwrap();
+            match fields.next() {
+                Some(level) => {
+                    let level = parse_level(level)?;
+                    logger.modules.push((first.to_string(), level));
+                }
+                None => logger.default = parse_level(first)?,
+            }
+        }
+        // Longer module paths are more specific, so they're checked first
+        logger.modules.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+        Ok(logger)
+    }
+
+    /// Installs the logger for the `log` macros.
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let max = self.modules.iter().map(|&(_, level)| level).fold(self.default, |a, b| a.max(b));
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max);
+        Ok(())
+    }
+
+    fn filter(&self, target: &str) -> LevelFilter {
+        self.modules.iter()
+            .find(|&&(ref module, _)| in_module(target, module))
+            .map_or(self.default, |&(_, level)| level)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let target = record.target();
+        let prefix = format!("{}::", CRATE_NAME);
+        let module = if target.starts_with(&prefix) { &target[prefix.len()..] } else { target };
+        eprintln!("{:<5} [{}] {}", record.level(), module, record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+/// Whether `target` is the module `module`, or inside of it. The game's
+/// modules match with or without the crate name.
+fn in_module(target: &str, module: &str) -> bool {
+    let within = |path: &str| target == path || target.starts_with(&format!("{}::", path));
+    within(module) || within(&format!("{}::{}", CRATE_NAME, module))
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level.trim().parse::<LevelFilter>().map_err(|_| format!("unknown log level `{}`", level))
+}
@@ -4,15 +4,20 @@
 //! his unsafe brother, Neferrious.
 //! Will Ferris be able to quench his thrist?
 
+extern crate game;
 #[macro_use]
 extern crate ggez;
+#[macro_use]
+extern crate log;
 extern crate nalgebra;
 extern crate ncollide;
 
-pub mod actors;
-mod game_inputs;
+use game::{actors, campaign, console, coords, cutscene, editor, game_inputs, generator, hot_reload, layers,
+           leaderboard, level, locale, logging, settings, shop, speedrun, telemetry, triggers, tuning, ui};
 
 use std::env;
+use std::fs;
+use std::io::Write;
 use std::path;
 use std::collections::{BTreeMap, LinkedList};
 use std::time::{Duration, Instant};
@@ -26,20 +31,39 @@ use ggez::event::{self, EventHandler, Keycode, Mod};
 use ggez::graphics;
 use ggez::graphics::{Color, DrawMode, Rect, Vector2, Point2, rectangle, set_color,
                       DrawParam, TextCached, TextFragment, Scale, HorizontalAlign as HAlign, Layout};
-use ggez::{Context, ContextBuilder, GameResult};
+use ggez::{Context, ContextBuilder, GameError, GameResult};
 use ggez::nalgebra as na;
 use ggez::nalgebra::{Isometry2};
 use ggez::timer;
-use actors::player::Player;
-use actors::coin::Coin;
-use actors::object::Object;
+use actors::player::{Player, TerrainContact};
+use actors::coin::{Coin, CoinKind};
+use actors::object::{Object, SENSOR_HALF_EXTENTS};
+use actors::enemy::{Enemy, ENEMY_SCALE, ENEMY_SCORE};
 use actors::types::{ActorType, CollisionObjectData};
+use campaign::{Campaign, Progress};
+use console::{Command, Console, Spawn};
+use coords::{Camera, ScreenPos, WorldPos};
+use cutscene::{ActorRef, Cutscene, CutsceneEvent, Script};
+use editor::{Editor, Tool};
 use game_inputs::{Direction, GameInput, InputEvent};
-use ncollide::shape::{Cuboid2, ShapeHandle2};
+use hot_reload::{Reload, ResourceWatcher};
+use layers::{InteractionMatrix, Layer, LayerMembership};
+use leaderboard::{Leaderboard, LeaderboardEntry, INITIALS_LEN};
+use logging::Logger;
+use level::{Checkpoint, GroundBlock, Level, Slope, TerrainKind, Volume, VolumeKind};
+use locale::Locale;
+use settings::Settings;
+use shop::{Purchase, Shop, ShopItem, ShopMessage};
+use speedrun::{Ghost, RunTimer, SplitKind};
+use telemetry::{Telemetry, TelemetryEvent};
+use triggers::{sensor_query, zone_layers, Trigger, TriggerEvent, Triggers};
+use tuning::TuningProfiles;
+use ui::{Anchor, Label, List, Shadow, TextStyle, UiEvent, UiInput};
+use ncollide::shape::{ConvexHull2, Cuboid2, ShapeHandle2};
 use ncollide::procedural::circle;
 use ncollide::world::{CollisionGroups, CollisionObjectHandle, CollisionWorld2, GeometricQueryType};
-use ncollide::events::{ContactEvent, ProximityEvent};
-use ncollide::query::Proximity;
+use ncollide::events::ContactEvent;
+use ncollide::query::Ray;
 use ncollide::narrow_phase::ContactAlgorithm;
 
 
@@ -54,6 +78,32 @@ const WINDOW_WIDTH:f32 = 1920.;
 const FERRIS_HEIGHT:f32 = 167.;
 const FERRIS_WIDTH:f32 = 226.;
 
+/// The level loaded when the game starts.
+const START_LEVEL: &str = "/levels/beach.lvl";
+
+/// The bonus awarded for collecting every coin in a level.
+const COMPLETION_BONUS: i32 = 5000;
+
+/// Contacts whose normal is within about 45 degrees of vertical count as
+/// ground (or ceiling) contacts; anything steeper is a wall.
+const GROUND_NORMAL: f32 = 0.7;
+
+/// Distance at which the player's contacts are reported before it actually
+/// touches the terrain, so resting on the ground stays a contact.
+const CONTACT_PREDICTION: f32 = 2.;
+
+/// How far below a grounded player the ground may drop away in a single step
+/// before the player starts falling instead of following it down.
+const GROUND_SNAP_DISTANCE: f32 = 24.;
+
+/// Height of the developer console when it is open.
+const CONSOLE_HEIGHT: f32 = 400.;
+
+/// Where the shop's text starts from the left of the screen, and the width
+/// its description and messages are wrapped at, inside its background.
+const SHOP_LEFT: f32 = 310.;
+const SHOP_TEXT_WIDTH: f32 = 1300.;
+
 /// ***************************************************************************
 /// # Assets
 /// 'Assets' contain the various game assets such as text font, music, sounds,
@@ -64,7 +114,6 @@ struct Assets {
 	player_image: graphics::Image,
 	coin_image: graphics::Image,
     vending_image: graphics::Image,
-	font: graphics::Font,
 	main_music: audio::Source,
     end_music: audio::Source,
 	jump: audio::Source,
@@ -76,12 +125,19 @@ impl Assets {
 		let player_image = graphics::Image::new(ctx, "/player.png")?;
 		let coin_image = graphics::Image::new(ctx, "/coin.png")?;
         let vending_image = graphics::Image::new(ctx, "/vendingMachine.png")?;
-		let font = graphics::Font::new(ctx, "/prstartk.ttf", 32)?;
 		let main_music = audio::Source::new(ctx, "/Rolemusic_-_07_-_Beach_Wedding_Dance.ogg")?;
         let end_music= audio::Source::new(ctx, "/Rolemusic_-_neogauge.ogg")?;
 		let jump = audio::Source::new(ctx, "/jump.wav")?;
 		let coin_jingle = audio::Source::new(ctx, "/coin_jingle.ogg")?;
-		Ok(Assets {player_image, coin_image, vending_image, font, main_music, end_music, jump, coin_jingle})
+		Ok(Assets {player_image, coin_image, vending_image, main_music, end_music, jump, coin_jingle})
+	}
+
+	/// Sets the volume of the music, and of the sound effects, from 0 to 1.
+	fn set_volumes(&mut self, music: f32, sounds: f32) {
+		self.main_music.set_volume(music);
+		self.end_music.set_volume(music);
+		self.jump.set_volume(sounds);
+		self.coin_jingle.set_volume(sounds);
 	}
 
 	fn actor_image(&mut self) -> &mut graphics::Image {
@@ -95,39 +151,68 @@ impl Assets {
     fn vending_image(&mut self) -> &mut graphics::Image {
         &mut self.vending_image
     }
+
+	/// Reloads the asset loaded from `path`, returning false if no asset
+	/// comes from there. The main music starts over if it was playing.
+	fn reload(&mut self, ctx: &mut Context, path: &str) -> GameResult<bool> {
+		match path {
+			"/player.png" => self.player_image = graphics::Image::new(ctx, path)?,
+			"/coin.png" => self.coin_image = graphics::Image::new(ctx, path)?,
+			"/vendingMachine.png" => self.vending_image = graphics::Image::new(ctx, path)?,
+			"/Rolemusic_-_07_-_Beach_Wedding_Dance.ogg" => {
+				let was_playing = self.main_music.playing();
+				self.main_music = audio::Source::new(ctx, path)?;
+				if was_playing {
+					self.main_music.play()?;
+				}
+			}
+			"/Rolemusic_-_neogauge.ogg" => self.end_music = audio::Source::new(ctx, path)?,
+			"/jump.wav" => self.jump = audio::Source::new(ctx, path)?,
+			"/coin_jingle.ogg" => self.coin_jingle = audio::Source::new(ctx, path)?,
+			_ => return Ok(false),
+		}
+		Ok(true)
+	}
 }
 
 
 
 
-/// Borrowed from GGEZ Astroblasto example
-/// This is used to transalte the world coordinate system which has both Y == 0
-/// and X == 0 being the origin (center of the screen), and converts it to the
-/// screen coordinate system which has the origin in the upper left of the
-/// screen with Y inverted (increasing in a downward direction).
-/// This helps with converting all items being rendred from the top-left.
-fn world_to_screen_coords(screen_width: u32, screen_height: u32, point: Vector2) -> Vector2 {
-    let width = WINDOW_WIDTH as f32;
-    let height = WINDOW_HEIGHT as f32;
-    let x = point.x + width / 2.0;
-    let y = height - (point.y + height / 2.0);
-    Vector2::new(x, y)
-}
-
 /// A function used to draw the actor graphic at its current position. This
-/// position is helped by the world_to_screen_coords() method listed earlier.
+/// position is converted to the screen by the `Camera`.
 fn draw_actor(
 	assets: &mut Assets,
 	ctx: &mut Context,
 	player: &Player,
-	world_coords: (u32, u32),) -> GameResult<()> {
+	camera: &Camera,) -> GameResult<()> {
+
+	let pos = camera.world_to_screen(WorldPos(player.pos)).0;
+	let image = assets.actor_image();
+	let scale = player.stats().scale;
+	let drawparams = graphics::DrawParam {
+		dest: Point2::new(pos.x, pos.y),
+		offset: graphics::Point2::new(0.0, 0.0),
+		scale: Point2::new(scale, scale),
+		..Default::default()
+	};
+
+	graphics::draw_ex(ctx, image, drawparams)
+}
+
+/// A function used to draw the best run's ghost as a translucent Ferris at the
+/// recorded world position.
+fn draw_ghost(
+	assets: &mut Assets,
+	ctx: &mut Context,
+	pos: Vector2,
+	camera: &Camera,) -> GameResult<()> {
 
-	let (screen_w, screen_h) = world_coords;
-	let pos = world_to_screen_coords(screen_w, screen_h, player.pos);
+	let pos = camera.world_to_screen(WorldPos(pos)).0;
 	let image = assets.actor_image();
 	let drawparams = graphics::DrawParam {
 		dest: Point2::new(pos.x, pos.y),
 		offset: graphics::Point2::new(0.0, 0.0),
+		color: Some(graphics::Color::new(1.0, 1.0, 1.0, 0.35)),
 		..Default::default()
 	};
 
@@ -135,19 +220,24 @@ fn draw_actor(
 }
 
 /// A function used to draw the coin graphic at its current position. This
-/// position is helped by the world_to_screen_coords() method listed earlier.
+/// position is converted to the screen by the `Camera`.
 fn draw_coin(
 	assets: &mut Assets,
 	ctx: &mut Context,
 	coin: &Coin,
-	world_coords: (u32, u32),) -> GameResult<()> {
+	camera: &Camera,) -> GameResult<()> {
 
-	let (screen_w, screen_h) = world_coords;
-	let pos = world_to_screen_coords(screen_w, screen_h, coin.pos);
+	let pos = camera.world_to_screen(WorldPos(coin.pos)).0;
 	let image = assets.coin_image();
+	let tint = match coin.kind {
+		CoinKind::Bronze => (205, 127, 50, 255),
+		CoinKind::Silver => (200, 200, 215, 255),
+		CoinKind::Gold => (255, 255, 255, 255),
+	};
 	let drawparams = graphics::DrawParam {
 		dest: Point2::new(pos.x, pos.y),
 		offset: graphics::Point2::new(0.0, 0.0),
+		color: Some(graphics::Color::from(tint)),
 		..Default::default()
 	};
 
@@ -156,16 +246,14 @@ fn draw_coin(
 
 /// ***************************************************************************
 /// A function used to draw the vending machine object graphic at its current
-/// position. This position is helped by the world_to_screen_coords() method
-/// listed earlier.
+/// position. This position is converted to the screen by the `Camera`.
 /// ***************************************************************************
 fn draw_vending(
     assets: &mut Assets,
     ctx: &mut Context,
     vending: &mut Object,
-    world_coords: (u32, u32),) -> GameResult<()> {
-    let (screen_w, screen_h) = world_coords;
-    let pos = world_to_screen_coords(screen_w, screen_h, vending.pos);
+    camera: &Camera,) -> GameResult<()> {
+    let pos = camera.world_to_screen(WorldPos(vending.pos)).0;
     let image = assets.vending_image();
     let drawparams = graphics::DrawParam {
         dest: Point2::new(pos.x, pos.y),
@@ -179,50 +267,443 @@ fn draw_vending(
 
 
 
+/// A function used to draw an enemy: one of Neferrious's minions, drawn as a
+/// smaller, darker Ferris.
+fn draw_enemy(
+    assets: &mut Assets,
+    ctx: &mut Context,
+    enemy: &Enemy,
+    camera: &Camera,) -> GameResult<()> {
+    let pos = camera.world_to_screen(WorldPos(enemy.pos)).0;
+    let image = assets.actor_image();
+    let drawparams = graphics::DrawParam {
+        dest: Point2::new(pos.x, pos.y),
+        offset: graphics::Point2::new(0.0, 0.0),
+        scale: Point2::new(ENEMY_SCALE, ENEMY_SCALE),
+        color: Some(graphics::Color::from((120, 40, 160, 255))),
+        ..Default::default()
+    };
+
+    graphics::draw_ex(ctx, image, drawparams)
+}
+
+/// A function used to draw a piece of terrain: blocks as filled rectangles and
+/// slopes as filled polygons. Breakable blocks are drawn darker, and one-way
+/// platforms lighter, so they stand out from the rest of the ground.
+fn draw_terrain(ctx: &mut Context, terrain: &Terrain, camera: &Camera) -> GameResult<()> {
+    let color = match terrain.kind {
+        TerrainKind::Solid => (194, 160, 100, 255),
+        TerrainKind::Breakable => (140, 90, 50, 255),
+        TerrainKind::OneWay => (225, 200, 150, 255),
+    };
+    set_color(ctx, graphics::Color::from(color))?;
+    match terrain.shape {
+        TerrainShape::Block(block) => {
+            let center = camera.world_to_screen(WorldPos(block.pos)).0;
+            rectangle(ctx, DrawMode::Fill, Rect::new(
+                center.x - block.half_extents.x,
+                center.y - block.half_extents.y,
+                block.half_extents.x * 2.,
+                block.half_extents.y * 2.))?;
+        }
+        TerrainShape::Slope(slope) => {
+            let points: Vec<Point2> = slope.corners().iter()
+                .map(|c| camera.world_to_screen(WorldPos(*c)).point())
+                .collect();
+            graphics::polygon(ctx, DrawMode::Fill, &points)?;
+        }
+    }
+    set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))
+}
+
+/// The geometry of a piece of terrain, as loaded from the level.
+#[derive(Clone, Copy)]
+enum TerrainShape {
+    Block(GroundBlock),
+    Slope(Slope),
+}
+
+/// `Terrain` is a piece of the level's terrain along with its collision object.
+/// Broken terrain is removed from the collision world but kept in the list, so
+/// the ids in the collision objects' data stay valid.
+struct Terrain {
+    handle: CollisionObjectHandle,
+    kind: TerrainKind,
+    shape: TerrainShape,
+    broken: bool,
+}
+
+/// A function used to draw a volume as a translucent rectangle.
+fn draw_volume(ctx: &mut Context, volume: &Volume, camera: &Camera) -> GameResult<()> {
+    let color = match volume.kind {
+        VolumeKind::Ladder => (150, 100, 40, 200),
+        VolumeKind::Vine => (40, 140, 50, 200),
+        VolumeKind::Water => (40, 110, 220, 120),
+    };
+    set_color(ctx, graphics::Color::from(color))?;
+    let center = camera.world_to_screen(WorldPos(volume.pos)).0;
+    rectangle(ctx, DrawMode::Fill, Rect::new(
+        center.x - volume.half_extents.x,
+        center.y - volume.half_extents.y,
+        volume.half_extents.x * 2.,
+        volume.half_extents.y * 2.))?;
+    set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))
+}
+
+/// A function used to draw a checkpoint as a translucent flag, which turns
+/// green once the checkpoint has been reached.
+fn draw_checkpoint(ctx: &mut Context, checkpoint: &Checkpoint, reached: bool, camera: &Camera) -> GameResult<()> {
+    let color = if reached { (60, 200, 90, 90) } else { (230, 230, 230, 60) };
+    set_color(ctx, graphics::Color::from(color))?;
+    let center = camera.world_to_screen(WorldPos(checkpoint.pos)).0;
+    rectangle(ctx, DrawMode::Fill, Rect::new(
+        center.x - checkpoint.half_extents.x,
+        center.y - checkpoint.half_extents.y,
+        checkpoint.half_extents.x * 2.,
+        checkpoint.half_extents.y * 2.))?;
+    set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))
+}
+
+/// `ContactOutcome` is the result of a contact event for the game state: the
+/// points earned, and whether the player was hurt.
+#[derive(Default)]
+struct ContactOutcome {
+    score: i32,
+    hurt: bool,
+}
+
 /// # Contact handler
 ///
 /// `handle_contact_event()` is used a collision event handler used to assist
-/// the collision events of the player with the ground, coin, and vending
-/// machine. This will be expanded to help with collisions of various ground
-/// and coin objects (required to help with more expansive level objects).
-fn handle_contact_event(player: &mut Player, coin: &mut Coin,  vending: &mut Object, world: &CollisionWorld2<f32, ()>, assets: &Assets, event: &ContactEvent, ctx: &mut Context) -> i32 {
-	let mut s = 0;
+/// the collision events of the player with enemies. Contacts that don't
+/// involve the player are ignored, and ground contacts are left to
+/// `resolve_terrain_contacts()`. Pickups, the goal and zones are sensors, and
+/// are handled through their trigger events instead.
+fn handle_contact_event(player: &mut Player, enemies: &mut [Enemy], world: &CollisionWorld2<f32, CollisionObjectData>, event: &ContactEvent) -> ContactOutcome {
+	let mut s = ContactOutcome::default();
     if let &ContactEvent::Started(collider1, collider2) = event {
 
     	let co1 = world.collision_object(collider1).unwrap();
     	let co2 = world.collision_object(collider2).unwrap();
-        // check if collision object is coin
-    	if co1.handle() == coin.getColHandle() || co2.handle() == coin.getColHandle() {
-    		if !coin.isPickedUp(){
-    			coin.pickUpCoin();
-    			println!("Picked up coin?: {:?}", coin.isPickedUp());
-    			s = 1337;
-    			let _ = assets.coin_jingle.play();
-    		}
-    	}
-        // check if collision object is the vending machine
-        else if co1.handle() == vending.getColHandle() || co2.handle() == vending.getColHandle() {
-            if !vending.isPickedUp(){
-                vending.pickUpObject();
-                let _ = assets.main_music.stop();
-                let _ = assets.end_music.play();
-                println!("Vending Machine Reached?: {:?}", vending.isPickedUp());
-            }
-        }
-        // else vending machine is the ground
-    	else{
-    		if player.grounded == false {
-				player.input(InputEvent::Landed);
-			}
-	    	let vector = co1.position().translation.vector.data;
+        // `other` is whatever the player collided with
+        let other = match (co1.data().actor, co2.data().actor) {
+            (ActorType::Player, _) => co2.data(),
+            (_, ActorType::Player) => co1.data(),
+            _ => return s,
+        };
+        // check if collision object is an enemy: a ground pound defeats it and
+        // bounces the player off, otherwise it hurts the player
+        if other.actor == ActorType::Enemy {
+            let enemy = &mut enemies[other.id];
+            if !enemy.is_defeated() {
+                if player.is_ground_pounding() {
+                    enemy.defeat();
+                    player.bounce();
+                    s.score = ENEMY_SCORE;
+                }
+                else if player.is_invincible() {
+                    enemy.defeat();
+                    s.score = ENEMY_SCORE;
+                }
+                else if !player.consume_shield() {
+                    s.hurt = true;
+                }
+            }
+        }
+    }
+    s
+}
 
-			let pos = world_to_screen_coords(ctx.conf.window_mode.height, ctx.conf.window_mode.width, Vector2::new(co1.position().translation.vector.data[0], co1.position().translation.vector.data[1]));
+/// # Terrain contacts
+///
+/// `resolve_terrain_contacts()` pushes the player back out of any terrain it
+/// has moved into, and sorts its contacts with the terrain by their normal
+/// into ground, wall and ceiling contacts for `Player::set_terrain_contact()`.
+/// Ground contacts are resolved straight up rather than along the normal, so
+/// the player walks up and down slopes instead of sliding off them.
+///
+/// One-way platforms only count when the player lands on them from above,
+/// and a ground pound smashes through breakable blocks instead of landing on
+/// them; their ids are returned, once each, so they can be removed from
+/// the level.
+fn resolve_terrain_contacts(player: &mut Player, world: &CollisionWorld2<f32, CollisionObjectData>, terrain_pieces: &[Terrain], layers: &InteractionMatrix) -> Vec<usize> {
+    let pounding = player.is_ground_pounding();
+    let mut terrain = TerrainContact::default();
+    let mut push = Vector2::new(0., 0.);
+    let mut broken = Vec::new();
+
+    for (co1, co2, contact) in world.contacts() {
+        // `normal` points from the player into the terrain, so it points down
+        // into the ground the player is standing on
+        let (normal, other) = match (co1.data().actor, co2.data().actor) {
+            (ActorType::Player, ActorType::Terrain) => (contact.normal.unwrap(), co2.data()),
+            (ActorType::Terrain, ActorType::Player) => (-contact.normal.unwrap(), co1.data()),
+            _ => continue,
+        };
+        let kind = terrain_pieces[other.id].kind;
+
+        if kind == TerrainKind::OneWay {
+            // Only land on a platform when falling onto its top surface: anything
+            // deeper than this step's fall means the player came from below.
+            let landing_depth = -player.velocity.y + CONTACT_PREDICTION;
+            if player.is_dropping_through() || player.velocity.y > 0. || -normal.y <= GROUND_NORMAL || contact.depth > landing_depth {
+                continue;
+            }
+        }
 
-			player.pos.y = pos.y + FERRIS_HEIGHT +50.;
-		}
+        if -normal.y > GROUND_NORMAL {
+            if pounding && kind == TerrainKind::Breakable {
+                // A block can touch the player at more than one point
+                if !broken.contains(&other.id) {
+                    broken.push(other.id);
+                }
+                continue;
+            }
+            terrain.ground = true;
+            terrain.on_platform = terrain.on_platform || kind == TerrainKind::OneWay;
+            if contact.depth > 0. {
+                push.y = push.y.max(contact.depth / -normal.y);
+            }
+            continue;
+        }
+        else if normal.y > GROUND_NORMAL {
+            terrain.ceiling = true;
+        }
+        else {
+            terrain.wall = Some(if normal.x > 0. { Direction::Right } else { Direction::Left });
+        }
+
+        if contact.depth > 0. {
+            push -= normal * contact.depth;
+        }
     }
-    // return int s: either 0 or x>0 (picked up coin)
-    s
+
+    // Keep a grounded player walking down a slope stuck to it, instead of
+    // stepping off into the air every step.
+    if !terrain.ground && player.grounded && player.velocity.y <= 0. && !player.in_special_move() {
+        if let Some(drop) = ground_below(player, world, terrain_pieces, layers) {
+            push.y -= drop;
+            terrain.ground = true;
+        }
+    }
+
+    player.pos += push;
+    player.set_terrain_contact(terrain);
+    broken
+}
+
+/// `ground_below()` casts a ray down from the bottom of the player and returns
+/// the distance to the ground if it is within `GROUND_SNAP_DISTANCE`.
+fn ground_below(player: &mut Player, world: &CollisionWorld2<f32, CollisionObjectData>, terrain_pieces: &[Terrain], layers: &InteractionMatrix) -> Option<f32> {
+    let player_handle = player.getColHandle();
+    let center = world.collision_object(player_handle)?.position().translation.vector;
+    let origin = Point2::new(center.x, center.y - player.half_extents().y + 1.);
+    let ray = Ray::new(origin, Vector2::new(0., -1.));
+    // The ray is cast as the player, but only looks for terrain
+    let groups = layers.groups(&Player::collision_layers().whitelist(&[Layer::Terrain]));
+
+    let mut nearest: Option<f32> = None;
+    for (object, hit) in world.interferences_with_ray(&ray, &groups) {
+        let kind = match object.data().actor {
+            ActorType::Terrain => terrain_pieces[object.data().id].kind,
+            _ => continue,
+        };
+        if kind == TerrainKind::OneWay && player.is_dropping_through() {
+            continue;
+        }
+        // The hit normal faces back up towards the player on walkable ground
+        if hit.normal.y > GROUND_NORMAL && hit.toi - 1. <= GROUND_SNAP_DISTANCE {
+            let distance = (hit.toi - 1.).max(0.);
+            nearest = Some(nearest.map_or(distance, |n: f32| n.min(distance)));
+        }
+    }
+    nearest
+}
+
+/// The table of leaderboard entries for `level`, centred on the screen
+/// `top` pixels down.
+fn leaderboard_text(font: &graphics::Font, locale: &Locale, board: &Leaderboard, level: &str, top: f32) -> Label {
+    let mut text = format!("{}\n\n", locale.format("leaderboard.title", &[("level", level.to_string())]));
+    let entries = board.entries(level);
+    if entries.is_empty() {
+        text.push_str(&locale.text("leaderboard.empty"));
+    }
+    for (rank, entry) in entries.iter().enumerate() {
+        text.push_str(&format!("{:>2}. {:<3} {:>6} {:>10}\n",
+            rank + 1, entry.initials, entry.score, leaderboard::format_time(entry.time)));
+    }
+    Label::new(text, TextStyle::new(font, 32., Color::from((255, 255, 255, 255))))
+        .wrapped(1200.)
+        .anchored(Anchor::Top, Vector2::new(0., top))
+}
+
+/// The message shown once a level is finished, leading on to the `next`
+/// level of the campaign if there is one.
+fn win_message(font: &graphics::Font, locale: &Locale, next: Option<&str>) -> Label {
+    let mut text = match next {
+        Some(next) => format!("{}  {}  ", locale.text("win.complete"), locale.format("win.next", &[("level", next.to_string())])),
+        None => format!("{}  {}  ", locale.text("win.congratulations"), locale.text("win.map")),
+    };
+    text.push_str(&locale.text("win.exit"));
+    Label::new(text, TextStyle::new(font, 40., Color::from((185, 30, 1, 255))))
+        .wrapped(1000.)
+        .centered()
+        .anchored(Anchor::Center, Vector2::new(0., -120.))
+}
+
+/// Loads the first of the locale's fonts that can be loaded, as the glyph
+/// font `TextCached` draws with. Glyphs the font is missing are drawn as
+/// empty boxes, as there is no fallback to the other fonts per glyph.
+fn load_font(ctx: &mut Context, locale: &Locale) -> GameResult<graphics::Font> {
+    let mut error = None;
+    for path in locale.fonts() {
+        match graphics::Font::new_glyph_font(ctx, path.as_str()) {
+            Ok(font) => return Ok(font),
+            Err(e) => {
+                debug!("Font {} can't be used for {}: {}", path, locale.code(), e);
+                error = Some(e);
+            }
+        }
+    }
+    Err(error.unwrap_or_else(|| GameError::ResourceLoadError(String::from("no fonts to load"))))
+}
+
+/// The title screen menu. The ids of its buttons are their keys in the
+/// string tables.
+fn title_menu(font: &graphics::Font, locale: &Locale) -> List {
+    let style = TextStyle::new(font, 40., Color::from((185, 30, 1, 255)));
+    List::new(style, Color::from((255, 255, 255, 255)))
+        .anchored(Anchor::Top, Vector2::new(0., 550.))
+        .spacing(50.)
+        .centered()
+        .button("menu.start", locale.text("menu.start"))
+        .button("menu.leaderboard", locale.text("menu.leaderboard"))
+        .button("menu.options", locale.text("menu.options"))
+        .button("menu.language", locale.format("menu.language", &[("language", locale.name())]))
+        .button("menu.quit", locale.text("menu.quit"))
+}
+
+/// The options menu, showing the current settings.
+fn options_menu(font: &graphics::Font, locale: &Locale, settings: &Settings) -> List {
+    let style = TextStyle::new(font, 40., Color::from((185, 30, 1, 255)));
+    List::new(style, Color::from((255, 255, 255, 255)))
+        .anchored(Anchor::Left, Vector2::new(360., 0.))
+        .spacing(50.)
+        .heading("options.title", locale.text("options.title"))
+        .slider("options.music", locale.text("options.music"), settings.music_volume, 0., 1., 0.1)
+        .slider("options.sounds", locale.text("options.sounds"), settings.sound_volume, 0., 1., 0.1)
+        .toggle("options.ghost", locale.text("options.ghost"), settings.show_ghost)
+        .button("options.back", locale.text("options.back"))
+}
+
+/// The world map: each world with its levels, which of them have been
+/// finished, and what opens the ones that are locked. Every level's button
+/// has the id `map.level`, so a level's index in the campaign is its row.
+fn world_map(font: &graphics::Font, locale: &Locale, campaign: &Campaign, progress: &Progress) -> List {
+    let style = TextStyle::new(font, 32., Color::from((185, 30, 1, 255)));
+    let mut map = List::new(style, Color::from((255, 255, 255, 255)))
+        .anchored(Anchor::TopLeft, Vector2::new(360., 240.));
+    let mut index = 0;
+    for world in &campaign.worlds {
+        map = map.heading("map.world", locale.text_or(&format!("world.{}", world.name), &world.name))
+            .colored(Color::from((228, 55, 23, 255)));
+        for level in &world.levels {
+            let unlocked = campaign.is_unlocked(index, progress);
+            let status = if progress.is_completed(&level.path) {
+                locale.text("map.cleared")
+            } else if unlocked {
+                String::new()
+            } else {
+                match campaign.unlock_requirement(index) {
+                    Some((previous, Some(coins))) => locale.format("map.locked_coins", &[
+                        ("level", previous),
+                        ("coins", coins.to_string()),
+                    ]),
+                    Some((previous, None)) => locale.format("map.locked", &[("level", previous)]),
+                    None => String::new(),
+                }
+            };
+            map = map.button("map.level", format!("{:<12} {}", level.title(), status).trim_end());
+            if !unlocked {
+                map = map.colored(Color::from((120, 120, 120, 255)));
+            }
+            index += 1;
+        }
+    }
+    map
+}
+
+/// `Hud` is the text drawn over the level while it is being played.
+struct Hud {
+    hello: Label,
+    score: Label,
+    time: Label,
+    coins: Label,
+    effects: Label,
+}
+
+impl Hud {
+    fn new(font: &graphics::Font, locale: &Locale) -> Hud {
+        let red = Color::from((228, 55, 23, 255));
+        let dark_red = Shadow { color: Color::from((146, 32, 27, 255)), ..Shadow::default() };
+        Hud {
+            hello: Label::new(locale.text("hud.hello"), TextStyle::new(font, 32., red).shadow(Some(dark_red)))
+                .anchored(Anchor::TopLeft, Vector2::new(10., 10.)),
+            score: Label::new("", TextStyle::new(font, 32., red).shadow(Some(dark_red)))
+                .anchored(Anchor::TopLeft, Vector2::new(10., 80.)),
+            time: Label::new("", TextStyle::new(font, 32., red))
+                .anchored(Anchor::TopRight, Vector2::new(-10., 10.)),
+            coins: Label::new("", TextStyle::new(font, 32., red))
+                .anchored(Anchor::TopLeft, Vector2::new(10., 150.)),
+            effects: Label::new("", TextStyle::new(font, 24., Color::from((255, 255, 255, 255))))
+                .anchored(Anchor::TopLeft, Vector2::new(10., 200.)),
+        }
+    }
+
+    fn queue(&self, ctx: &mut Context) -> GameResult<()> {
+        for label in &[&self.hello, &self.score, &self.time, &self.coins, &self.effects] {
+            label.queue(ctx)?;
+        }
+        Ok(())
+    }
+}
+
+/// The name of a shop item in the locale's language.
+fn item_name(locale: &Locale, item: &ShopItem) -> String {
+    locale.text_or(&format!("shop.{}", item.key), item.name)
+}
+
+/// The vending machine's menu: a button for each item, with the item's key
+/// as its id, and one to leave the shop.
+fn shop_menu(font: &graphics::Font, locale: &Locale, shop: &Shop) -> List {
+    let style = TextStyle::new(font, 32., Color::from((185, 30, 1, 255)));
+    let mut menu = List::new(style, Color::from((255, 255, 255, 255)))
+        .anchored(Anchor::TopLeft, Vector2::new(SHOP_LEFT, 270.))
+        .spacing(10.);
+    for item in shop.items() {
+        menu = menu.button(item.key, format!("{:<20} {:>5}", item_name(locale, item), item.price));
+    }
+    menu.button("shop.leave", locale.text("shop.leave"))
+}
+
+/// `Screen` is the part of the game that is currently being shown, and decides
+/// where `MainState` sends its updates, draws and key presses.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Screen {
+    Title,
+    Playing,
+    Shop,
+    EnterInitials,
+    Won,
+    Leaderboard,
+    /// The level select, listing the campaign's worlds and levels.
+    WorldMap,
+    /// The level editor, with the game paused.
+    Editing,
+    /// The options menu, reached from the title screen.
+    Options,
+    /// A cutscene, with the game paused and the keys going to the dialogue.
+    Cutscene,
 }
 
 /// # MainState
@@ -232,94 +713,1294 @@ fn handle_contact_event(player: &mut Player, coin: &mut Coin,  vending: &mut Obj
 /// a list of states instead of calling a single MainState.
 struct MainState {
 	image1: graphics::Image,
-    text: graphics::Text,
+    hud: Hud,
     frames: usize,
     assets: Assets,
+    level: Level,
+    level_path: String,
+    /// Watches the resources directory for edits, when running from a
+    /// cargo project directory.
+    watcher: Option<ResourceWatcher>,
+    /// The resources directory on disk, which the editor saves levels into.
+    resources_dir: Option<path::PathBuf>,
+    editor: Editor,
+    /// Where the mouse is, in world coordinates.
+    mouse: Vector2,
+    tuning: TuningProfiles,
+    /// The tuning profile chosen on the command line, which overrides the level's.
+    tuning_override: Option<String>,
+    terrain: Vec<Terrain>,
+    triggers: Triggers,
+    /// Which of the level's checkpoints have been reached, and where the
+    /// player respawns as a result.
+    checkpoints_reached: Vec<bool>,
+    respawn: Vector2,
     player: Player,
-    coin: Coin,
+    coins: Vec<Coin>,
+    enemies: Vec<Enemy>,
+    deaths: u32,
     vending: Object,
     score: i32,
-    score_display: graphics::Text,
+    bonus_awarded: bool,
+    wallet: i32,
+    shop: Shop,
+    shop_menu: List,
     win_bool: bool,
-    win_display: BTreeMap<&'static str, TextCached>,
-    screen_width: u32,
-    screen_height: u32,
+    win_display: Label,
+    camera: Camera,
+    console: Console,
+    telemetry: Telemetry,
+    /// The player can't be hurt, toggled from the console.
+    god_mode: bool,
+    /// Whether a console command has changed the run being played.
+    cheated: bool,
+    /// How many game steps run per update, and the fraction of a step left
+    /// over from the last update.
+    time_scale: f64,
+    pending_steps: f64,
     gameInput: GameInput,
-    world: CollisionWorld2<f32, ()>,
-
+    world: CollisionWorld2<f32, CollisionObjectData>,
+    layers: InteractionMatrix,
+    screen: Screen,
+    title_menu: List,
+    options_menu: List,
+    world_map: List,
+    settings: Settings,
+    ui_font: graphics::Font,
+    locale: Locale,
+    /// The codes of the languages the game can be played in.
+    languages: Vec<String>,
+    leaderboard: Leaderboard,
+    campaign: Campaign,
+    progress: Progress,
+    /// The campaign level selected on the world map.
+    map_index: usize,
+    initials: String,
+    run_time: f64,
+    run_timer: RunTimer,
+    recording: Ghost,
+    best_ghost: Option<Ghost>,
+    cutscene: Option<Cutscene>,
+    /// The screen shown once the cutscene is over.
+    after_cutscene: Screen,
+    /// The speakers' portraits for the cutscene playing, by their path.
+    portraits: BTreeMap<String, graphics::Image>,
+    cutscene_sounds: Vec<audio::Source>,
 }
 
 impl MainState {
-    fn new(ctx: &mut Context) -> GameResult<MainState> {
+    fn new(ctx: &mut Context, level_path: &str) -> GameResult<MainState> {
         // The ttf file will be in the resources directory. Later, we
         // will mount that directory so we can omit it
         // in the path here.
 
-        let assets = Assets::new(ctx)?;
-        let text = graphics::Text::new(ctx, "Hello Ferris!", &assets.font)?;
-        let score_disp = graphics::Text::new(ctx, "Score:", &assets.font)?;
-        let mut win_disp = BTreeMap::new();
-        let image1 = graphics::Image::new(ctx, "/beach.png")?;
+        let mut assets = Assets::new(ctx)?;
+        let level = Level::load(ctx, level_path)?;
+        let languages = locale::languages(ctx);
+        let language = locale::initial_language(ctx, &languages);
+        let locale = Locale::load(ctx, &language).or_else(|e| {
+            warn!("Unable to load the {} strings: {}", language, e);
+            Locale::load(ctx, locale::DEFAULT_LANGUAGE)
+        })?;
+        let ui_font = load_font(ctx, &locale)?;
+        let settings = Settings::load(ctx);
+        assets.set_volumes(settings.music_volume, settings.sound_volume);
+        let image1 = graphics::Image::new(ctx, level.background.as_str())?;
         graphics::set_background_color(ctx, (0, 0, 0, 255).into());
 
-        let pos = world_to_screen_coords(ctx.conf.window_mode.height, ctx.conf.window_mode.width, Vector2::new(-1920., 0.));
-
-        let mut player = actors::player::Player::new(pos, 1.0, Some(Direction::Right));
-        let mut coin = actors::coin::Coin::new(pos);
-        let mut vending = actors::object::Object::new(Vector2::new(1920./2. - 275., -1080./4. + 350.));
+        let mut player = actors::player::Player::new(level.spawn, 1.0, Some(Direction::Right));
+        player.set_abilities(level.abilities);
+        let tuning = TuningProfiles::load(ctx)?;
+        let coins = level.coins.iter().map(|c| actors::coin::Coin::new(c.pos, c.kind)).collect();
+        let enemies = level.enemies.iter().map(|e| Enemy::new(e.pos, e.patrol)).collect();
+        let mut vending = actors::object::Object::new(level.goal);
         let _ = assets.main_music.play();
+        let leaderboard = Leaderboard::load(ctx);
+        let campaign = Campaign::load(ctx, START_LEVEL)?;
+        let progress = Progress::load(ctx);
+        let map_index = campaign.position(level_path).unwrap_or(0);
+        let best_ghost = Ghost::load(ctx, &level.name);
+        let layers = InteractionMatrix::load(ctx)?;
+        let checkpoints_reached = vec![false; level.checkpoints.len()];
+        let respawn = level.spawn;
         // set MainState
         let mut s = MainState {
         	image1,
-        	text,
+            hud: Hud::new(&ui_font, &locale),
         	frames: 0,
         	assets,
+            level,
+            level_path: level_path.to_string(),
+            watcher: None,
+            resources_dir: None,
+            editor: Editor::new(),
+            mouse: Vector2::new(0., 0.),
+            tuning,
+            tuning_override: None,
+            terrain: Vec::new(),
+            triggers: Triggers::new(),
+            checkpoints_reached,
+            respawn,
         	player,
-        	coin,
+        	coins,
+            enemies,
+            deaths: 0,
             vending,
         	score: 0,
-        	score_display: score_disp,
+            bonus_awarded: false,
+            wallet: progress.wallet,
+            shop: Shop::new(),
+            shop_menu: shop_menu(&ui_font, &locale, &Shop::new()),
             win_bool: false,
-            win_display: win_disp,
-        	screen_width: ctx.conf.window_mode.width,
-        	screen_height: ctx.conf.window_mode.height,
+            win_display: win_message(&ui_font, &locale, None),
+            camera: Camera::new(ctx.conf.window_mode.width, ctx.conf.window_mode.height),
+            console: Console::new(),
+            telemetry: Telemetry::disabled(),
+            god_mode: false,
+            cheated: false,
+            time_scale: 1.,
+            pending_steps: 0.,
         	gameInput: GameInput::new(),
         	world: CollisionWorld2::new(0.02),
+            layers,
+            screen: Screen::Title,
+            title_menu: title_menu(&ui_font, &locale),
+            options_menu: options_menu(&ui_font, &locale, &settings),
+            world_map: world_map(&ui_font, &locale, &campaign, &progress),
+            settings,
+            ui_font,
+            locale,
+            languages,
+            leaderboard,
+            campaign,
+            progress,
+            map_index,
+            initials: String::new(),
+            run_time: 0.,
+            run_timer: RunTimer::new(),
+            recording: Ghost::new(),
+            best_ghost,
+            cutscene: None,
+            after_cutscene: Screen::Playing,
+            portraits: BTreeMap::new(),
+            cutscene_sounds: Vec::new(),
         };
-        /// modify score value to default
-        let score_str = format!("Score: {}", 0);
-        let score_text = graphics::Text::new(ctx, &score_str, &s.assets.font).unwrap();
-        s.score_display = score_text;
-        // modify & set win message
-        let test_font = graphics::Font::new_glyph_font(ctx, "/prstartk.ttf")?;
-        let mut text = TextCached::new_empty()?;
-        text.add_fragment("Congratulations!!  ");
-        text.add_fragment("You have helped Ferris find a coin, ");
-        text.add_fragment("and quench his thirst for Safety!  ");
-        text.add_fragment("Press Escape at any time to exit.");
-        text.set_font(test_font.clone(), Scale::uniform(40.0))
-            .set_bounds(
-                Point2::new(1000.0, 1000.0),
-                Some(Layout::default().h_align(HAlign::Center)),
-            );
-        s.win_display.insert("Win_Message", text.clone());
-                
+        s.apply_tuning();
+        s.build_world();
         Ok(s)
     }
 
+    /// Adds the collision objects for the level's ground, its volumes, the
+    /// player, every coin, and the vending machine to the collision world,
+    /// each on its own collision layers.
+    fn build_world(&mut self) {
+        let groups = self.layers.groups(&LayerMembership::new(&[Layer::Terrain]));
+        let query = GeometricQueryType::Contacts(0., 0.);
+
+        for block in self.level.ground.clone() {
+            self.add_block(block);
+        }
+
+        for slope in self.level.slopes.clone() {
+            // The hull's points are relative to the center of the ramp
+            let corners = slope.corners();
+            let center = corners.iter().fold(Vector2::new(0., 0.), |sum, c| sum + c) / corners.len() as f32;
+            let points = corners.iter().map(|c| Point2::from_coordinates(c - center)).collect();
+            let shape = ShapeHandle2::new(ConvexHull2::new(points));
+            let data = CollisionObjectData::new(ActorType::Terrain, self.terrain.len(), None);
+            let handle = self.add_collision_entity(Isometry2::new(center, 0.), shape, groups, query, data);
+            self.terrain.push(Terrain { handle, kind: TerrainKind::Solid, shape: TerrainShape::Slope(slope), broken: false });
+        }
+
+        // Zones are sensors that only report when the player is inside of them
+        let sensor = sensor_query();
+        let groups = self.layers.groups(&zone_layers());
+        for (i, volume) in self.level.volumes.clone().iter().enumerate() {
+            let shape = ShapeHandle2::new(Cuboid2::new(volume.half_extents));
+            let data = CollisionObjectData::new(ActorType::Zone, i, None);
+            let handle = self.add_collision_entity(Isometry2::new(volume.pos, 0.), shape, groups, sensor, data);
+            self.triggers.register(handle, Trigger::Volume(i));
+        }
+        for (i, checkpoint) in self.level.checkpoints.clone().iter().enumerate() {
+            let shape = ShapeHandle2::new(Cuboid2::new(checkpoint.half_extents));
+            let data = CollisionObjectData::new(ActorType::Zone, i, None);
+            let handle = self.add_collision_entity(Isometry2::new(checkpoint.pos, 0.), shape, groups, sensor, data);
+            self.triggers.register(handle, Trigger::Checkpoint(i));
+        }
+
+        // Create the object shapes to use for our collision handles
+        let playerShape = self.player.collision_shape();
+        let (sensor_hw, sensor_hh) = SENSOR_HALF_EXTENTS;
+        let vendShape = ShapeHandle2::new(Cuboid2::new(Vector2::new(sensor_hw, sensor_hh)));
+
+        // Actors move their collision objects into place on their first update
+        let pos = Vector2::new(0., 0.);
+        // Set the player, coin, and vending machine collision handles and add
+        // them to their respective `Actor` objects. Coins and the vending
+        // machine are sensors, so the player passes through them.
+        let player_query = GeometricQueryType::Contacts(CONTACT_PREDICTION, 0.);
+        let player_groups = self.layers.groups(&Player::collision_layers());
+        let player_data = CollisionObjectData::new(ActorType::Player, 0, Some(self.player.velocity));
+        let player_collision_handle = self.add_collision_entity(Isometry2::new(pos, 0.), playerShape, player_groups, player_query, player_data);
+        self.player.set_col_handle(player_collision_handle);
+        for i in 0..self.coins.len() {
+            if !self.coins[i].isPickedUp() {
+                self.add_coin_object(i);
+            }
+        }
+        for i in 0..self.enemies.len() {
+            self.add_enemy_object(i);
+        }
+        let vending_groups = self.layers.groups(&Object::collision_layers());
+        let vending_data = CollisionObjectData::new(ActorType::Object, 0, None);
+        let vending_collision_handle = self.add_collision_entity(Isometry2::new(pos, 0.), vendShape, vending_groups, sensor, vending_data);
+        self.vending.set_col_handle(vending_collision_handle);
+        self.triggers.register(vending_collision_handle, Trigger::Goal);
+    }
+
+    /// Plays with the tuning profile `name` instead of the level's.
+    fn override_tuning(&mut self, name: String) {
+        self.tuning_override = Some(name);
+        self.apply_tuning();
+    }
+
+    /// Gives the player the stats of the tuning profile selected for the level.
+    fn apply_tuning(&mut self) {
+        let (name, stats) = self.tuning.select(&[self.tuning_override.as_ref().map(|n| n.as_str()), self.level.tuning.as_ref().map(|n| n.as_str())]);
+        info!("Using the {} tuning profile", name);
+        self.player.set_base_stats(stats);
+    }
+
+    /// Starts recording gameplay telemetry for the session.
+    fn enable_telemetry(&mut self, ctx: &mut Context) {
+        match Telemetry::start(ctx) {
+            Ok(telemetry) => self.telemetry = telemetry,
+            Err(e) => warn!("Unable to record telemetry: {}", e),
+        }
+    }
+
+    /// Starts watching `resources` for edited files to reload.
+    fn watch_resources(&mut self, resources: path::PathBuf) {
+        self.watcher = Some(ResourceWatcher::new(resources.clone()));
+        self.resources_dir = Some(resources);
+    }
+
+    /// `hot_reload()` reloads every watched resource that was edited since
+    /// the last poll. A resource that fails to load, such as a level file
+    /// that is only half written, is reported and the old one is kept.
+    fn hot_reload(&mut self, ctx: &mut Context) {
+        let changed = match self.watcher {
+            Some(ref mut watcher) => watcher.poll(),
+            None => return,
+        };
+        for path in changed {
+            let result = match Reload::classify(&path) {
+                Some(Reload::Level) if path == self.level_path => self.reload_level(ctx).map(|_| true),
+                Some(Reload::Image) if path == self.level.background => {
+                    graphics::Image::new(ctx, path.as_str()).map(|image| { self.image1 = image; true })
+                }
+                Some(Reload::Image) | Some(Reload::Sound) => self.assets.reload(ctx, &path),
+                Some(Reload::Tuning) => TuningProfiles::load(ctx).map(|tuning| {
+                    self.tuning = tuning;
+                    self.apply_tuning();
+                    true
+                }),
+                _ => Ok(false),
+            };
+            match result {
+                Ok(true) => info!("Reloaded {}", path),
+                Ok(false) => (),
+                Err(e) => warn!("Couldn't reload {}: {}", path, e),
+            }
+        }
+    }
+
+    /// `reload_level()` reloads the current level file and rebuilds the
+    /// collision world from it. The player keeps its position, and coins
+    /// that were picked up stay picked up if they haven't moved.
+    fn reload_level(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let level = Level::load(ctx, &self.level_path)?;
+        if level.background != self.level.background {
+            self.image1 = graphics::Image::new(ctx, level.background.as_str())?;
+        }
+        self.apply_level(level);
+        Ok(())
+    }
+
+    /// `apply_level()` replaces the level being played and rebuilds the
+    /// collision world from it. The player keeps its position, and coins
+    /// that were picked up stay picked up if they haven't moved.
+    fn apply_level(&mut self, level: Level) {
+        let picked_up: Vec<Vector2> = self.coins.iter().filter(|c| c.isPickedUp()).map(|c| c.pos).collect();
+        self.coins = level.coins.iter().map(|c| Coin::new(c.pos, c.kind)).collect();
+        for coin in self.coins.iter_mut().filter(|c| picked_up.contains(&c.pos)) {
+            coin.pickUpCoin();
+        }
+        self.enemies = level.enemies.iter().map(|e| Enemy::new(e.pos, e.patrol)).collect();
+        self.vending = Object::new(level.goal);
+        self.checkpoints_reached.resize(level.checkpoints.len(), false);
+        self.player.set_abilities(level.abilities);
+        self.level = level;
+        self.apply_tuning();
+
+        self.world = CollisionWorld2::new(0.02);
+        self.terrain.clear();
+        self.triggers = Triggers::new();
+        self.build_world();
+    }
+
+    /// `load_level()` starts the level at `path` afresh, with the player at
+    /// its spawn point and the score, deaths, timer and ghost reset. The
+    /// wallet goes back to the coins held when the last level was finished.
+    fn load_level(&mut self, ctx: &mut Context, path: &str) -> GameResult<()> {
+        let level = Level::load(ctx, path)?;
+        self.image1 = graphics::Image::new(ctx, level.background.as_str())?;
+        self.best_ghost = Ghost::load(ctx, &level.name);
+        self.level_path = path.to_string();
+        self.player = Player::new(level.spawn, 1.0, Some(Direction::Right));
+        self.respawn = level.spawn;
+        self.checkpoints_reached = vec![false; level.checkpoints.len()];
+        self.coins.clear();
+        self.apply_level(level);
+
+        self.editor = Editor::new();
+        self.score = 0;
+        self.deaths = 0;
+        self.bonus_awarded = false;
+        self.win_bool = false;
+        // God mode and the time scale carry over to the next run
+        self.cheated = self.god_mode || self.time_scale != 1.;
+        self.wallet = self.progress.wallet;
+        self.run_time = 0.;
+        self.run_timer = RunTimer::new();
+        self.recording = Ghost::new();
+        self.pending_steps = 0.;
+        let _ = self.assets.end_music.stop();
+        if !self.assets.main_music.playing() {
+            let _ = self.assets.main_music.play();
+        }
+        Ok(())
+    }
+
+    /// The campaign level after the one being played, if there is one.
+    fn next_level_index(&self) -> Option<usize> {
+        self.campaign.position(&self.level_path)
+            .map(|index| index + 1)
+            .filter(|&index| index < self.campaign.len())
+    }
+
+    /// Plays the level selected on the world map, if it is unlocked.
+    fn play_selected(&mut self, ctx: &mut Context) {
+        if !self.campaign.is_unlocked(self.map_index, &self.progress) {
+            return;
+        }
+        let path = match self.campaign.get(self.map_index) {
+            Some(level) => level.path.clone(),
+            None => return,
+        };
+        match self.load_level(ctx, &path) {
+            Ok(()) => self.start_run(ctx),
+            Err(e) => warn!("Unable to load {}: {}", path, e),
+        }
+    }
+
+    /// Moves on from a finished level: straight to the next level of the
+    /// campaign, or to the world map after the last one.
+    fn continue_campaign(&mut self, ctx: &mut Context) {
+        match self.next_level_index() {
+            Some(next) => {
+                self.map_index = next;
+                self.play_selected(ctx);
+            }
+            None => self.open_world_map(),
+        }
+    }
+
+    /// Shows the world map, with the focus on the level last played.
+    fn open_world_map(&mut self) {
+        self.world_map = world_map(&self.ui_font, &self.locale, &self.campaign, &self.progress);
+        self.world_map.focus_row("map.level", self.map_index);
+        self.screen = Screen::WorldMap;
+    }
+
+    /// Switches between playing and the level editor.
+    fn toggle_editor(&mut self) {
+        self.screen = if self.screen == Screen::Editing { Screen::Playing } else { Screen::Editing };
+    }
+
+    /// Rebuilds the game from the level after an edit.
+    fn level_edited(&mut self) {
+        let level = self.level.clone();
+        self.apply_level(level);
+    }
+
+    /// Moves the entity of an item being dragged to its new place in the
+    /// level, leaving the rest of the game as it is. Ground blocks are the
+    /// first pieces of terrain, in the level's order.
+    fn item_moved(&mut self, item: editor::Item) {
+        match item {
+            editor::Item::Ground(i) => {
+                let block = self.level.ground[i];
+                // A smashed block has already left the collision world
+                if !self.terrain[i].broken {
+                    self.world.set_position(self.terrain[i].handle, Isometry2::new(block.pos, 0.));
+                }
+                self.terrain[i].shape = TerrainShape::Block(block);
+            }
+            editor::Item::Coin(i) => self.coins[i].pos = self.level.coins[i].pos,
+            editor::Item::Goal => self.vending.pos = self.level.goal,
+            editor::Item::Spawn => {},
+        }
+    }
+
+    /// Saves the edited level over its level file. Levels are written into
+    /// the resources directory when running from the project directory, and
+    /// to the user data directory otherwise.
+    fn save_level(&mut self, ctx: &mut Context) {
+        let contents = self.level.serialize();
+        let result = match self.resources_dir {
+            Some(ref dir) => fs::write(dir.join(self.level_path.trim_start_matches('/')), contents)
+                .map_err(|e| e.to_string()),
+            None => ctx.filesystem.create(&self.level_path)
+                .and_then(|mut file| file.write_all(contents.as_bytes()).map_err(|e| e.into()))
+                .map_err(|e| e.to_string()),
+        };
+        match result {
+            Ok(()) => {
+                self.editor.saved();
+                info!("Saved {}", self.level_path);
+            }
+            Err(e) => warn!("Unable to save {}: {}", self.level_path, e),
+        }
+    }
+
+    /// Draws the editor's grid, the handles of the items it can pick, the
+    /// block being dragged out, and the editing help.
+    fn draw_editor(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let (width, height) = self.camera.size();
+        let top_left = editor::snap(self.camera.screen_to_world(ScreenPos(Vector2::new(0., 0.))).0);
+        set_color(ctx, Color::new(1., 1., 1., 0.08))?;
+        let mut x = top_left.x;
+        while self.camera.world_to_screen(WorldPos(Vector2::new(x, 0.))).0.x < width {
+            let sx = self.camera.world_to_screen(WorldPos(Vector2::new(x, 0.))).0.x;
+            rectangle(ctx, DrawMode::Fill, Rect::new(sx, 0., 1., height))?;
+            x += editor::GRID;
+        }
+        let mut y = top_left.y;
+        while self.camera.world_to_screen(WorldPos(Vector2::new(0., y))).0.y < height {
+            let sy = self.camera.world_to_screen(WorldPos(Vector2::new(0., y))).0.y;
+            rectangle(ctx, DrawMode::Fill, Rect::new(0., sy, width, 1.))?;
+            y -= editor::GRID;
+        }
+
+        let hovered = editor::item_at(&self.level, self.mouse);
+        let mut handles: Vec<(editor::Item, Vector2)> = self.level.coins.iter().enumerate()
+            .map(|(i, c)| (editor::Item::Coin(i), c.pos))
+            .collect();
+        handles.push((editor::Item::Goal, self.level.goal));
+        handles.push((editor::Item::Spawn, self.level.spawn));
+        for (item, pos) in handles {
+            let color = if Some(item) == hovered { (255, 220, 60, 200) } else { (255, 255, 255, 120) };
+            set_color(ctx, graphics::Color::from(color))?;
+            let center = self.camera.world_to_screen(WorldPos(pos)).0;
+            let size = editor::HANDLE_SIZE;
+            rectangle(ctx, DrawMode::Line(2.), Rect::new(center.x - size, center.y - size, size * 2., size * 2.))?;
+        }
+        if let Some(editor::Item::Ground(i)) = hovered {
+            let block = self.level.ground[i];
+            set_color(ctx, graphics::Color::from((255, 220, 60, 200)))?;
+            let center = self.camera.world_to_screen(WorldPos(block.pos)).0;
+            rectangle(ctx, DrawMode::Line(3.), Rect::new(center.x - block.half_extents.x, center.y - block.half_extents.y,
+                block.half_extents.x * 2., block.half_extents.y * 2.))?;
+        }
+        if let Some((pos, half_extents)) = self.editor.new_block() {
+            set_color(ctx, graphics::Color::from((194, 160, 100, 160)))?;
+            let center = self.camera.world_to_screen(WorldPos(pos)).0;
+            rectangle(ctx, DrawMode::Fill, Rect::new(center.x - half_extents.x, center.y - half_extents.y,
+                half_extents.x * 2., half_extents.y * 2.))?;
+        }
+        set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+
+        let tool = match self.editor.tool() {
+            Tool::Coin(kind) => self.locale.format("editor.tool.coin", &[("kind", kind.name().to_string())]),
+            tool => self.locale.text(&format!("editor.tool.{}", tool.name())),
+        };
+        let snapped = editor::snap(self.mouse);
+        let status = self.locale.format("editor.status", &[
+            ("level", self.level_path.clone()),
+            ("unsaved", String::from(if self.editor.is_unsaved() { "*" } else { "" })),
+            ("tool", tool),
+            ("x", snapped.x.to_string()),
+            ("y", snapped.y.to_string()),
+        ]);
+        let mut text = TextCached::new(format!("{}\n{}", status, self.locale.text("editor.help")))?;
+        text.set_font(self.ui_font.clone(), Scale::uniform(20.0));
+        ui::queue_text(ctx, &text, Point2::new(10., height - 100.), graphics::Color::from((255, 255, 255, 255)), Some(Shadow::default()));
+        TextCached::draw_queued(ctx, DrawParam::default())
+    }
+
+    /// Adds a block of ground to the collision world and the level's terrain.
+    fn add_block(&mut self, block: GroundBlock) {
+        let groups = self.layers.groups(&LayerMembership::new(&[Layer::Terrain]));
+        let shape = ShapeHandle2::new(Cuboid2::new(block.half_extents));
+        let data = CollisionObjectData::new(ActorType::Terrain, self.terrain.len(), None);
+        let handle = self.add_collision_entity(Isometry2::new(block.pos, 0.), shape, groups, GeometricQueryType::Contacts(0., 0.), data);
+        self.terrain.push(Terrain { handle, kind: block.kind, shape: TerrainShape::Block(block), broken: false });
+    }
+
+    /// Adds the sensor for the coin at `index` to the collision world. The
+    /// coin moves it into place on its first update.
+    fn add_coin_object(&mut self, index: usize) {
+        let shape = ShapeHandle2::new(Cuboid2::new(Vector2::new(0.1, 0.1)));
+        let groups = self.layers.groups(&Coin::collision_layers());
+        let data = CollisionObjectData::new(ActorType::Coin, index, None);
+        let handle = self.add_collision_entity(Isometry2::new(self.coins[index].pos, 0.), shape, groups, sensor_query(), data);
+        self.coins[index].set_col_handle(handle);
+        self.triggers.register(handle, Trigger::Coin(index));
+    }
+
+    /// Adds the collision object for the enemy at `index` to the collision world.
+    fn add_enemy_object(&mut self, index: usize) {
+        let groups = self.layers.groups(&Enemy::collision_layers());
+        let data = CollisionObjectData::new(ActorType::Enemy, index, Some(self.enemies[index].velocity()));
+        let iso = Isometry2::new(self.enemies[index].pos, 0.);
+        let handle = self.add_collision_entity(iso, Enemy::collision_shape(), groups, GeometricQueryType::Contacts(0., 0.), data);
+        self.enemies[index].set_col_handle(handle);
+    }
+
+    /// `run_command()` carries out a command typed into the developer
+    /// console, returning what to print back to it.
+    fn run_command(&mut self, ctx: &mut Context, command: Command) -> String {
+        self.cheated = self.cheated || command.is_cheat();
+        match command {
+            Command::Teleport(pos) => {
+                self.player.respawn(pos);
+                format!("Teleported to {} {}", pos.x, pos.y)
+            }
+            Command::GiveCoins(amount) => {
+                self.wallet += amount;
+                format!("Wallet: {} coins", self.wallet)
+            }
+            Command::SetScore(score) => {
+                self.score = score;
+                format!("Score: {}", score)
+            }
+            Command::Spawn(Spawn::Coin(pos, kind)) => {
+                self.coins.push(Coin::new(pos, kind));
+                let index = self.coins.len() - 1;
+                self.add_coin_object(index);
+                format!("Spawned a {} coin at {} {}", kind.name(), pos.x, pos.y)
+            }
+            Command::Spawn(Spawn::Enemy(pos, patrol)) => {
+                self.enemies.push(Enemy::new(pos, patrol));
+                let index = self.enemies.len() - 1;
+                self.add_enemy_object(index);
+                format!("Spawned an enemy at {} {}", pos.x, pos.y)
+            }
+            Command::Spawn(Spawn::Object(pos)) => {
+                self.vending.pos = pos;
+                format!("Moved the vending machine to {} {}", pos.x, pos.y)
+            }
+            Command::Spawn(Spawn::Terrain(pos, half_extents)) => {
+                self.add_block(GroundBlock { pos, half_extents, kind: TerrainKind::Solid });
+                format!("Spawned ground at {} {}", pos.x, pos.y)
+            }
+            Command::ToggleNoclip => {
+                let noclip = !self.player.is_noclip();
+                self.player.set_noclip(noclip);
+                format!("Noclip {}", if noclip { "on" } else { "off" })
+            }
+            Command::ToggleGod => {
+                self.god_mode = !self.god_mode;
+                format!("God mode {}", if self.god_mode { "on" } else { "off" })
+            }
+            Command::ReloadLevel => match self.reload_level(ctx) {
+                Ok(()) => format!("Reloaded {}", self.level_path),
+                Err(e) => format!("Couldn't reload {}: {}", self.level_path, e),
+            },
+            Command::TimeScale(scale) => {
+                self.time_scale = scale;
+                self.pending_steps = 0.;
+                format!("Time scale: {}", scale)
+            }
+            Command::Dump => {
+                let dump = self.dump_state();
+                info!("{}", dump);
+                dump
+            }
+            Command::Help => console::HELP.to_string(),
+            Command::Clear => {
+                self.console.clear();
+                String::new()
+            }
+        }
+    }
+
+    /// Describes the state of the game, for the console's `dump` command.
+    fn dump_state(&self) -> String {
+        let p = &self.player;
+        let collected = self.coins.iter().filter(|c| c.isPickedUp()).count();
+        let defeated = self.enemies.iter().filter(|e| e.is_defeated()).count();
+        let reached = self.checkpoints_reached.iter().filter(|&&r| r).count();
+        format!("level {} ({})\n\
+            player pos {:.1} {:.1} vel {:.1} {:.1} state {:?} grounded {}\n\
+            score {} wallet {} deaths {} coins {}/{} enemies defeated {}/{} checkpoints {}/{}\n\
+            run {:.2}s time scale {} noclip {} god {}",
+            self.level.name, self.level_path,
+            p.pos.x, p.pos.y, p.velocity.x, p.velocity.y, p.currentState, p.grounded,
+            self.score, self.wallet, self.deaths, collected, self.coins.len(), defeated, self.enemies.len(), reached, self.checkpoints_reached.len(),
+            self.run_timer.time(), self.time_scale, p.is_noclip(), self.god_mode)
+    }
+
+    /// Draws the developer console over the top of the screen.
+    fn draw_console(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let (width, _) = self.camera.size();
+        set_color(ctx, Color::new(0., 0., 0., 0.75))?;
+        rectangle(ctx, DrawMode::Fill, Rect::new(0., 0., width, CONSOLE_HEIGHT))?;
+        set_color(ctx, Color::new(1., 1., 1., 1.))?;
+
+        let mut text = TextCached::new_empty()?;
+        for line in self.console.lines() {
+            text.add_fragment(format!("{}\n", line));
+        }
+        text.add_fragment(format!("> {}_", self.console.input()));
+        text.set_font(self.ui_font.clone(), Scale::uniform(20.0));
+        let h = text.height(ctx) as f32;
+        text.queue(ctx, Point2::new(10., CONSOLE_HEIGHT - h - 10.), Some(graphics::Color::from((255, 255, 255, 255))));
+        TextCached::draw_queued(ctx, DrawParam::default())
+    }
+
+    /// `step()` advances the game by one physics step: the actors move, the
+    /// collision world is updated, and the contacts and triggers it reports
+    /// are handled.
+    fn step(&mut self) {
+    	// The player's position is the left of its body, which has to stay in view
+    	let (left, right) = self.camera.visible_x();
+    	let right = right - self.player.half_extents().x * 2.;
+    	if self.player.pos.x > right || self.player.pos.x < left {
+    		self.player.pos.x = self.player.pos.x.max(left).min(right);
+    		self.player.velocity.x = 0.;
+    		self.player.input(InputEvent::UpdateMovement(None));
+    	}
+    	self.player.advance();
+        let player_handle = self.player.getColHandle();
+    	self.player.update(&mut self.world);
+        // A resized player has a new collision object, which gets its own proximity events
+        if self.player.getColHandle() != player_handle {
+            self.triggers.clear_inside();
+            self.player.set_volume(None);
+        }
+
+        if self.run_timer.is_running() {
+            self.recording.record(self.player.pos);
+            self.telemetry.record(self.run_timer.time(), TelemetryEvent::Position { pos: self.player.pos });
+        }
+        self.run_timer.tick();
+
+    	for coin in self.coins.iter_mut() {
+    		if !coin.isPickedUp(){
+    			coin.update(&mut self.world);
+    		}
+    	}
+
+    	for enemy in self.enemies.iter_mut() {
+    		enemy.step();
+    		enemy.update(&mut self.world);
+    	}
+
+        self.vending.update(&mut self.world);
+
+    	self.world.update();
+    	
+        let mut hurt = false;
+    	if self.world.contacts().count() > 0 {
+    		
+			for event in self.world.contact_events() {
+    			let s = handle_contact_event(&mut self.player, &mut self.enemies, &self.world, event);
+
+    			self.score = self.score + s.score;
+                hurt = hurt || s.hurt;
+			}
+
+		}
+
+        let player_handle = self.player.getColHandle();
+        for event in self.triggers.update(&self.world, player_handle) {
+            self.handle_trigger(event);
+        }
+
+        // With noclip on the player ignores the terrain altogether
+        let broken = if self.player.is_noclip() {
+            Vec::new()
+        } else {
+            resolve_terrain_contacts(&mut self.player, &self.world, &self.terrain, &self.layers)
+        };
+        for id in broken {
+            self.terrain[id].broken = true;
+            self.world.remove(&[self.terrain[id].handle]);
+        }
+
+        if hurt && !self.god_mode {
+            self.kill_player();
+        }
+
+        if !self.bonus_awarded && !self.coins.is_empty() && self.coins.iter().all(|c| c.isPickedUp()) {
+            self.bonus_awarded = true;
+            self.score += COMPLETION_BONUS;
+        }
+
+        if self.vending.isPickedUp() && !self.win_bool {
+            self.open_shop();
+        }
+    }
+
+    /// Sends the player back to the last checkpoint reached, or the level's
+    /// spawn point, after being hurt.
+    fn kill_player(&mut self) {
+        self.deaths += 1;
+        info!("Ferris was caught by Neferrious's minion ({} deaths)", self.deaths);
+        let event = TelemetryEvent::Death { pos: self.player.pos, deaths: self.deaths };
+        self.telemetry.record(self.run_timer.time(), event);
+        self.player.respawn(self.respawn);
+    }
+
+    /// `handle_trigger()` reacts to the player entering, staying in, or
+    /// leaving a sensor. Coins are picked up and removed from the collision
+    /// world, checkpoints move the respawn point, and the goal opens the shop.
+    /// The player moves as the volumes it is in while it stays in them, and
+    /// stops climbing or swimming when it leaves them.
+    fn handle_trigger(&mut self, event: TriggerEvent) {
+        match event {
+            TriggerEvent::Enter(Trigger::Coin(i)) => {
+                let coin = &mut self.coins[i];
+                if coin.isPickedUp() {
+                    return;
+                }
+                coin.pickUpCoin();
+                debug!("Picked up {} coin", coin.kind.name());
+                let event = TelemetryEvent::Pickup { kind: coin.kind.name().to_string(), pos: coin.pos };
+                self.telemetry.record(self.run_timer.time(), event);
+                let handle = coin.getColHandle();
+                coin.removeColHandle();
+                self.world.remove(&[handle]);
+                self.triggers.unregister(handle);
+
+                self.score += coin.value();
+                self.wallet += coin.value();
+                self.run_timer.split(SplitKind::Coin);
+                let _ = self.assets.coin_jingle.play();
+            }
+            TriggerEvent::Enter(Trigger::Checkpoint(i)) => {
+                if !self.checkpoints_reached[i] {
+                    self.checkpoints_reached[i] = true;
+                    self.respawn = self.level.checkpoints[i].respawn();
+                    self.run_timer.split(SplitKind::Checkpoint);
+                    info!("Checkpoint {} reached", i + 1);
+                    let event = TelemetryEvent::Checkpoint { index: i, pos: self.player.pos };
+                    self.telemetry.record(self.run_timer.time(), event);
+                }
+            }
+            TriggerEvent::Enter(Trigger::Goal) => {
+                if !self.vending.isPickedUp() {
+                    self.vending.pickUpObject();
+                    info!("Reached the vending machine");
+                }
+            }
+            TriggerEvent::Enter(Trigger::Volume(_))
+            | TriggerEvent::Stay(Trigger::Volume(_))
+            | TriggerEvent::Exit(Trigger::Volume(_)) => {
+                let volume = self.current_volume();
+                self.player.set_volume(volume);
+            }
+            // Pickups, checkpoints and the goal only react to being entered
+            TriggerEvent::Stay(_) | TriggerEvent::Exit(_) => {},
+        }
+    }
+
+    /// The kind of volume the player should move as. Water takes precedence
+    /// over ladders and vines.
+    fn current_volume(&self) -> Option<VolumeKind> {
+        self.triggers.inside()
+            .filter_map(|trigger| match trigger {
+                Trigger::Volume(i) => Some(self.level.volumes[i].kind),
+                _ => None,
+            })
+            .fold(None, |current, kind| match current {
+                Some(VolumeKind::Water) => current,
+                _ => Some(kind),
+            })
+    }
+
     // Add collision object to the current state. This is used to add player
-    // and environmental objects for future potential collision handling.
-    pub fn add_collision_entity(&mut self, isometry: Isometry2<f32>, shape_handle: ShapeHandle2<f32>, groups: CollisionGroups, query: GeometricQueryType<f32>) -> CollisionObjectHandle {
-		self.world.add(isometry, shape_handle, groups, query, ())
+    // and environmental objects for future potential collision handling. The
+    // `data` identifies the entity the collision object belongs to.
+    pub fn add_collision_entity(&mut self, isometry: Isometry2<f32>, shape_handle: ShapeHandle2<f32>, groups: CollisionGroups, query: GeometricQueryType<f32>, data: CollisionObjectData) -> CollisionObjectHandle {
+		self.world.add(isometry, shape_handle, groups, query, data)
 	}
 
-    // Update the score display text
-	fn update_ui(&mut self, ctx: &mut Context) {
-		let score_str = format!("Score: {}", self.score);
-		let score_text = graphics::Text::new(ctx, &score_str, &self.assets.font).unwrap();
-		self.score_display = score_text;
+    /// `set_language()` switches the game to the language `code`, with its
+    /// fonts, and saves the choice for the next time the game starts.
+    fn set_language(&mut self, ctx: &mut Context, code: &str) -> GameResult<()> {
+        let locale = Locale::load(ctx, code)?;
+        self.ui_font = load_font(ctx, &locale)?;
+        self.locale = locale;
+        self.rebuild_ui();
+        let next = self.next_level_index().and_then(|i| self.campaign.get(i)).map(|level| level.title());
+        self.win_display = win_message(&self.ui_font, &self.locale, next.as_ref().map(|n| n.as_str()));
+        if let Err(e) = locale::save_language(ctx, code) {
+            warn!("Unable to save the language: {}", e);
+        }
+        info!("Switched the language to {}", code);
+        Ok(())
+    }
 
-	}
+    /// Rebuilds the HUD and menus, such as after a change of language, keeping
+    /// the focus where it was.
+    fn rebuild_ui(&mut self) {
+        self.hud = Hud::new(&self.ui_font, &self.locale);
+        let focused = self.title_menu.focused();
+        self.title_menu = title_menu(&self.ui_font, &self.locale);
+        if let Some(id) = focused {
+            self.title_menu.focus(id);
+        }
+        let focused = self.options_menu.focused();
+        self.options_menu = options_menu(&self.ui_font, &self.locale, &self.settings);
+        if let Some(id) = focused {
+            self.options_menu.focus(id);
+        }
+        self.world_map = world_map(&self.ui_font, &self.locale, &self.campaign, &self.progress);
+        self.world_map.focus_row("map.level", self.map_index);
+        let focused = self.shop_menu.focused();
+        self.shop_menu = shop_menu(&self.ui_font, &self.locale, &self.shop);
+        if let Some(id) = focused {
+            self.shop_menu.focus(id);
+        }
+    }
+
+    /// Saves the settings after a change in the options menu.
+    fn save_settings(&self, ctx: &mut Context) {
+        if let Err(e) = self.settings.save(ctx) {
+            warn!("Unable to save the settings: {}", e);
+        }
+    }
+
+    /// Switches to the next language the game has been translated to.
+    fn next_language(&mut self, ctx: &mut Context) {
+        let current = self.languages.iter().position(|code| code == self.locale.code()).unwrap_or(0);
+        let code = self.languages[(current + 1) % self.languages.len()].clone();
+        if let Err(e) = self.set_language(ctx, &code) {
+            warn!("Unable to switch the language to {}: {}", code, e);
+        }
+    }
+
+    /// Leaves the title screen, playing the level's intro first if it has
+    /// one. The run timer itself starts on the first input.
+    fn start_run(&mut self, ctx: &mut Context) {
+        self.screen = Screen::Playing;
+        self.telemetry.record(0., TelemetryEvent::RunStart { level: self.level.name.clone() });
+        if let Some(intro) = self.level.intro.clone() {
+            self.play_cutscene(ctx, &intro);
+        }
+    }
+
+    /// `play_cutscene()` pauses the game on the current screen to play the
+    /// cutscene script at `path`, returning to the screen afterwards. A
+    /// script that can't be loaded is skipped.
+    fn play_cutscene(&mut self, ctx: &mut Context, path: &str) {
+        let path = self.locale.localized_path(ctx, path);
+        let script = match Script::load(ctx, &path) {
+            Ok(script) => script,
+            Err(e) => {
+                warn!("Unable to load cutscene {}: {}", path, e);
+                return;
+            }
+        };
+        self.portraits.clear();
+        for (_, speaker) in script.speakers() {
+            if let Some(ref portrait) = speaker.portrait {
+                match graphics::Image::new(ctx, portrait.as_str()) {
+                    Ok(image) => { self.portraits.insert(portrait.clone(), image); }
+                    Err(e) => warn!("Unable to load portrait {}: {}", portrait, e),
+                }
+            }
+        }
+        // Ferris stops where he is, and the keys go to the dialogue until it ends
+        self.player.input(InputEvent::UpdateMovement(None));
+        self.player.velocity.x = 0.;
+        self.cutscene = Some(Cutscene::new(script));
+        self.after_cutscene = self.screen;
+        self.screen = Screen::Cutscene;
+    }
+
+    /// Plays `dt` seconds of the cutscene, carrying out what it asks for, and
+    /// ends it once the script is finished.
+    fn update_cutscene(&mut self, ctx: &mut Context, dt: f64) {
+        let events = match self.cutscene {
+            Some(ref mut cutscene) => cutscene.update(dt),
+            None => Vec::new(),
+        };
+        for event in events {
+            match event {
+                CutsceneEvent::Move(ActorRef::Player, offset) => self.player.pos += offset,
+                CutsceneEvent::Move(ActorRef::Vending, offset) => self.vending.pos += offset,
+                CutsceneEvent::Move(ActorRef::Enemy(i), offset) => match self.enemies.get_mut(i) {
+                    Some(enemy) => enemy.pos += offset,
+                    None => warn!("The cutscene moves enemy {}, but the level has {}", i, self.enemies.len()),
+                },
+                CutsceneEvent::Pan(offset) => {
+                    let center = self.camera.center().0;
+                    self.camera.look_at(WorldPos(center + offset));
+                }
+                CutsceneEvent::PlaySound(path) => match audio::Source::new(ctx, path.as_str()) {
+                    Ok(sound) => {
+                        let _ = sound.play();
+                        // Sounds stop when dropped, so they are kept until they finish
+                        self.cutscene_sounds.retain(|s| s.playing());
+                        self.cutscene_sounds.push(sound);
+                    }
+                    Err(e) => warn!("Unable to play {}: {}", path, e),
+                },
+            }
+        }
+        if self.cutscene.as_ref().map_or(true, |c| c.is_finished()) {
+            self.cutscene = None;
+            self.portraits.clear();
+            self.camera.look_at(WorldPos(Vector2::new(0., 0.)));
+            self.screen = self.after_cutscene;
+        }
+    }
+
+    /// Called once the vending machine has been reached. A run faster than the
+    /// best ghost replaces it, and runs that make it onto the leaderboard ask
+    /// for the player's initials before the win screen.
+    fn finish_run(&mut self, ctx: &mut Context) {
+        self.win_bool = true;
+        let _ = self.assets.main_music.stop();
+        let _ = self.assets.end_music.play();
+        self.run_timer.stop();
+        self.run_time = self.run_timer.time();
+        let event = TelemetryEvent::Complete { time: self.run_time, score: self.score, deaths: self.deaths };
+        self.telemetry.record(self.run_time, event);
+        self.recording.finish(&self.run_timer);
+
+        // Finishing the level opens the next one, and banks the coins held
+        // unless the console could have handed them out
+        self.progress.complete(&self.level_path);
+        if !self.cheated {
+            self.progress.wallet = self.wallet;
+        }
+        if let Err(e) = self.progress.save(ctx) {
+            warn!("Unable to save progress: {}", e);
+        }
+        let next = self.next_level_index().and_then(|i| self.campaign.get(i)).map(|level| level.title());
+        self.win_display = win_message(&self.ui_font, &self.locale, next.as_ref().map(|n| n.as_str()));
+
+        // Runs helped along by the console don't count as records
+        if self.cheated {
+            info!("Not recording the run, as console commands were used");
+        }
+        let is_best = match self.best_ghost {
+            Some(ref best) => self.run_time < best.time,
+            None => true,
+        };
+        if is_best && !self.cheated {
+            if let Err(e) = self.recording.save(ctx, &self.level.name) {
+                warn!("Unable to save ghost: {}", e);
+            }
+            self.best_ghost = Some(std::mem::replace(&mut self.recording, Ghost::new()));
+        }
+
+        if !self.cheated && self.leaderboard.qualifies(&self.level.name, self.score, self.run_time) {
+            self.initials.clear();
+            self.screen = Screen::EnterInitials;
+        }
+        else {
+            self.screen = Screen::Won;
+        }
+        if let Some(outro) = self.level.outro.clone() {
+            self.play_cutscene(ctx, &outro);
+        }
+    }
+
+    /// Opens the vending machine's shop, pausing the level.
+    fn open_shop(&mut self) {
+        self.vending.release();
+        self.shop.open();
+        self.shop_menu = shop_menu(&self.ui_font, &self.locale, &self.shop);
+        self.screen = Screen::Shop;
+    }
+
+    /// Buys the highlighted shop item, or leaves the shop if "Leave" is
+    /// highlighted.
+    fn shop_select(&mut self, ctx: &mut Context) {
+        if self.shop.leave_selected() {
+            self.close_shop();
+            return;
+        }
+        match self.shop.buy(&mut self.wallet) {
+            Some(Purchase::Effect(effect)) => {
+                self.player.add_effect(effect);
+                let _ = self.assets.coin_jingle.play();
+            }
+            Some(Purchase::Finish) => self.finish_run(ctx),
+            None => {},
+        }
+    }
+
+    /// Goes back to the level, moving the player in whichever direction is
+    /// still held now that the shop has stopped taking the keys.
+    fn close_shop(&mut self) {
+        self.screen = Screen::Playing;
+        self.player.input(InputEvent::UpdateMovement(self.gameInput.held_dirs()));
+    }
+
+    /// Draws the vending machine's menu over the level, with the highlighted
+    /// item's description and what the shopkeeper last said below it.
+    fn draw_shop(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let size = Vector2::new(1400., 780.);
+        let corner = Anchor::Top.place(size, Vector2::new(0., 150.));
+        set_color(ctx, graphics::Color::new(0.0, 0.0, 0.0, 0.75))?;
+        rectangle(ctx, DrawMode::Fill, Rect::new(corner.x, corner.y, size.x, size.y))?;
+        set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+
+        let style = TextStyle::new(&self.ui_font, 32., Color::from((255, 255, 255, 255)));
+        Label::new(self.locale.format("shop.title", &[("coins", self.wallet.to_string())]), style.clone())
+            .anchored(Anchor::TopLeft, Vector2::new(SHOP_LEFT, 190.))
+            .queue(ctx)?;
+        if let Some(item) = self.shop.items().get(self.shop.selected) {
+            let description = self.locale.text_or(&format!("shop.{}.description", item.key), item.description);
+            Label::new(description, style.clone())
+                .wrapped(SHOP_TEXT_WIDTH)
+                .anchored(Anchor::TopLeft, Vector2::new(SHOP_LEFT, 710.))
+                .queue(ctx)?;
+        }
+        let message = match self.shop.message {
+            ShopMessage::Silent => String::new(),
+            ShopMessage::Greeting => self.locale.text("shop.greeting"),
+            ShopMessage::TooExpensive { ref item, wallet } => self.locale.format("shop.too_expensive", &[
+                ("item", item_name(&self.locale, item)),
+                ("price", item.price.to_string()),
+                ("coins", wallet.to_string()),
+            ]),
+            ShopMessage::Enjoy(ref item) => self.locale.format("shop.enjoy", &[("item", item_name(&self.locale, item))]),
+        };
+        Label::new(message, style)
+            .wrapped(SHOP_TEXT_WIDTH)
+            .anchored(Anchor::TopLeft, Vector2::new(SHOP_LEFT, 810.))
+            .queue(ctx)?;
+        self.shop_menu.draw(ctx)
+    }
+
+    /// Records the finished run under the entered initials and saves the table.
+    fn submit_initials(&mut self, ctx: &mut Context) {
+        let initials = if self.initials.is_empty() { leaderboard::NO_INITIALS } else { self.initials.as_str() };
+        let entry = LeaderboardEntry::new(initials, self.score, self.run_time);
+        self.leaderboard.submit(&self.level.name, entry);
+        if let Err(e) = self.leaderboard.save(ctx) {
+            warn!("Unable to save leaderboard: {}", e);
+        }
+        self.screen = Screen::Won;
+    }
+
+    /// `menu_input()` passes a key or gamepad press to the menu on screen,
+    /// and acts on what the player did with it.
+    fn menu_input(&mut self, ctx: &mut Context, input: UiInput) {
+        match self.screen {
+            Screen::Title => match self.title_menu.input(input) {
+                // Levels outside the campaign, such as generated ones, start straight away
+                Some(UiEvent::Pressed("menu.start")) if self.campaign.position(&self.level_path).is_some() => {
+                    self.open_world_map();
+                }
+                Some(UiEvent::Pressed("menu.start")) => self.start_run(ctx),
+                Some(UiEvent::Pressed("menu.leaderboard")) => self.screen = Screen::Leaderboard,
+                Some(UiEvent::Pressed("menu.options")) => self.screen = Screen::Options,
+                Some(UiEvent::Pressed("menu.language")) => self.next_language(ctx),
+                Some(UiEvent::Pressed("menu.quit")) | Some(UiEvent::Back) => ctx.quit().unwrap(),
+                _ => {},
+            },
+            Screen::Options => match self.options_menu.input(input) {
+                Some(UiEvent::Changed("options.music", volume)) => {
+                    self.settings.music_volume = volume;
+                    self.assets.set_volumes(self.settings.music_volume, self.settings.sound_volume);
+                    self.save_settings(ctx);
+                }
+                Some(UiEvent::Changed("options.sounds", volume)) => {
+                    self.settings.sound_volume = volume;
+                    self.assets.set_volumes(self.settings.music_volume, self.settings.sound_volume);
+                    self.save_settings(ctx);
+                    // A jump lets the player hear the new volume
+                    let _ = self.assets.jump.play();
+                }
+                Some(UiEvent::Toggled("options.ghost", on)) => {
+                    self.settings.show_ghost = on;
+                    self.save_settings(ctx);
+                }
+                Some(UiEvent::Pressed("options.back")) | Some(UiEvent::Back) => self.screen = Screen::Title,
+                _ => {},
+            },
+            Screen::WorldMap => {
+                let event = self.world_map.input(input);
+                if let Some(("map.level", row)) = self.world_map.focused_row() {
+                    self.map_index = row;
+                }
+                match event {
+                    Some(UiEvent::Pressed("map.level")) => self.play_selected(ctx),
+                    Some(UiEvent::Back) => self.screen = Screen::Title,
+                    _ => {},
+                }
+            }
+            Screen::Shop => {
+                let event = self.shop_menu.input(input);
+                // Leave is the last row, past the items
+                let focused = self.shop_menu.focused();
+                let selected = {
+                    let items = self.shop.items();
+                    items.iter().position(|item| Some(item.key) == focused).unwrap_or(items.len())
+                };
+                self.shop.selected = selected;
+                match event {
+                    Some(UiEvent::Pressed(_)) => self.shop_select(ctx),
+                    Some(UiEvent::Back) => self.close_shop(),
+                    _ => {},
+                }
+            }
+            Screen::Leaderboard => match input {
+                UiInput::Activate | UiInput::Back => self.screen = Screen::Title,
+                _ => {},
+            },
+            Screen::Won => match input {
+                UiInput::Activate => self.continue_campaign(ctx),
+                UiInput::Back => ctx.quit().unwrap(),
+                _ => {},
+            },
+            _ => {},
+        }
+    }
+
+    /// `draw_hud()` draws the text over the level: the score, the run timer
+    /// with the difference between the latest split and the same split of
+    /// the best run, the coins collected out of the level's total, and the
+    /// player's active status effects with how long they have left.
+    fn draw_hud(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.hud.score.set_text(self.locale.format("hud.score", &[("score", self.score.to_string())]));
+
+        let mut time = self.locale.format("hud.time", &[("time", leaderboard::format_time(self.run_timer.time()))]);
+        let splits = self.run_timer.splits();
+        if let Some(last) = splits.last() {
+            let delta = self.best_ghost.as_ref().and_then(|best| best.split_delta(splits.len() - 1, last));
+            if let Some(delta) = delta {
+                let sign = if delta > 0. { "+" } else { "-" };
+                time.push_str(&format!("\n{}{:.3}", sign, delta.abs()));
+            }
+        }
+        self.hud.time.set_text(time);
+
+        let collected = self.coins.iter().filter(|c| c.isPickedUp()).count();
+        let args = [("collected", collected.to_string()), ("total", self.coins.len().to_string())];
+        self.hud.coins.set_text(self.locale.format("hud.coins", &args));
+
+        let mut effects = String::new();
+        for effect in self.player.effects().iter() {
+            let name = self.locale.text(&format!("effect.{}", effect.kind.key()));
+            match effect.remaining {
+                Some(remaining) => effects.push_str(&format!("{} {:.0}s\n", name, remaining.ceil())),
+                None => effects.push_str(&format!("{}\n", name)),
+            };
+        }
+        self.hud.effects.set_text(effects);
+
+        self.hud.queue(ctx)?;
+        TextCached::draw_queued(ctx, DrawParam::default())
+    }
+
+    /// Draws the line of dialogue being spoken, if any, in a box along the
+    /// bottom of the screen with the speaker's portrait beside it.
+    fn draw_cutscene(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let dialogue = match self.cutscene.as_ref().and_then(|c| c.dialogue()) {
+            Some(dialogue) => dialogue,
+            None => return Ok(()),
+        };
+        let background = Rect::new(60., 740., 1800., 300.);
+        set_color(ctx, graphics::Color::new(0.0, 0.0, 0.0, 0.75))?;
+        rectangle(ctx, DrawMode::Fill, background)?;
+        set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+
+        let mut text_x = background.x + 40.;
+        if let Some(portrait) = dialogue.speaker.portrait.as_ref().and_then(|p| self.portraits.get(p)) {
+            let size = background.h - 60.;
+            let scale = size / portrait.width().max(portrait.height()) as f32;
+            graphics::draw_ex(ctx, portrait, DrawParam {
+                dest: Point2::new(background.x + 30., background.y + 30.),
+                scale: Point2::new(scale, scale),
+                ..Default::default()
+            })?;
+            text_x += size + 20.;
+        }
+
+        let mut name = TextCached::new(dialogue.speaker.name.clone())?;
+        name.set_font(self.ui_font.clone(), Scale::uniform(32.0));
+        ui::queue_text(ctx, &name, Point2::new(text_x, background.y + 30.), graphics::Color::from((228, 55, 23, 255)), Some(Shadow::default()));
+
+        let mut text = TextCached::new_empty()?;
+        text.add_fragment(format!("{}\n\n", dialogue.text));
+        for (i, choice) in dialogue.choices.iter().enumerate() {
+            let cursor = if i == dialogue.selected { ">" } else { " " };
+            text.add_fragment(format!("{} {}\n", cursor, choice));
+        }
+        text.set_font(self.ui_font.clone(), Scale::uniform(28.0))
+            .set_bounds(
+                Point2::new(background.x + background.w - text_x - 40., background.h - 90.),
+                Some(Layout::default().h_align(HAlign::Left)),
+            );
+        ui::queue_text(ctx, &text, Point2::new(text_x, background.y + 90.), graphics::Color::from((255, 255, 255, 255)), Some(Shadow::default()));
+        TextCached::draw_queued(ctx, DrawParam::default())
+    }
+
+    /// Draws the title screen, with its menu.
+    fn draw_title(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let style = TextStyle::new(&self.ui_font, 56., Color::from((228, 55, 23, 255)));
+        Label::new(self.locale.text("title"), style)
+            .anchored(Anchor::Top, Vector2::new(0., 250.))
+            .queue(ctx)?;
+        self.title_menu.draw(ctx)
+    }
+
+    /// Draws the world map, with the coins banked so far below it.
+    fn draw_world_map(&mut self, ctx: &mut Context) -> GameResult<()> {
+        Label::new(self.locale.text("map.title"), TextStyle::new(&self.ui_font, 56., Color::from((228, 55, 23, 255))))
+            .anchored(Anchor::Top, Vector2::new(0., 100.))
+            .queue(ctx)?;
+        let coins = self.locale.format("map.coins", &[("coins", self.progress.wallet.to_string())]);
+        Label::new(coins, TextStyle::new(&self.ui_font, 32., Color::from((255, 255, 255, 255))))
+            .anchored(Anchor::BottomLeft, Vector2::new(360., -80.))
+            .queue(ctx)?;
+        self.world_map.draw(ctx)
+    }
+
+    /// Draws the leaderboard for the current level at the given height.
+    fn draw_leaderboard(&mut self, ctx: &mut Context, top: f32) -> GameResult<()> {
+        leaderboard_text(&self.ui_font, &self.locale, &self.leaderboard, &self.level.name, top).queue(ctx)?;
+        TextCached::draw_queued(ctx, DrawParam::default())
+    }
+
+    /// Draws the prompt used to enter initials for a new leaderboard entry.
+    fn draw_initials_prompt(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let mut entered = self.initials.clone();
+        while entered.len() < INITIALS_LEN {
+            entered.push('_');
+        }
+        let time = leaderboard::format_time(self.run_time);
+        let text = format!("{}\n\n{}\n\n{}",
+            self.locale.format("initials.high_score", &[("time", time)]),
+            self.locale.format("initials.enter", &[("initials", entered)]),
+            self.locale.text("initials.done"));
+        Label::new(text, TextStyle::new(&self.ui_font, 40., Color::from((185, 30, 1, 255))))
+            .wrapped(1400.)
+            .centered()
+            .anchored(Anchor::Top, Vector2::new(0., 400.))
+            .queue(ctx)?;
+        TextCached::draw_queued(ctx, DrawParam::default())
+    }
 }
 
 // Then we implement the `ggez:event::EventHandler` trait on it, which
@@ -337,43 +2018,26 @@ impl event::EventHandler for MainState {
         // timer will offset the forced time updates of a manipulated frame update call.
     	const DESIRED_FPS: u32 = 60;
 
-    	while timer::check_update_time( _ctx, DESIRED_FPS) {
-
-        	if (self.player.pos.x > (WINDOW_WIDTH / 2.) - 226.) || (self.player.pos.x < - (WINDOW_WIDTH /2.)) {
-        		if self.player.pos.x > (WINDOW_WIDTH / 2.) - 226. {
-    				self.player.pos.x = (WINDOW_WIDTH / 2.) - 227.;
-    			}
-    			else {
-    				self.player.pos.x = -WINDOW_WIDTH / 2. + 5.;
-    			}
-        		self.player.velocity.x = 0.;
-        		self.player.input(InputEvent::UpdateMovement(None));
-        	}
-        	self.player.advance();
-        	self.player.update(_ctx, &mut self.world);
-
-        	if !self.coin.isPickedUp(){
-        		self.coin.update(_ctx, &mut self.world);
-        	}
-
-            self.vending.update(_ctx, &mut self.world);
-
-        	self.world.update();
-        	
-        	if self.world.contacts().count() > 0 {
-        		
-    			for event in self.world.contact_events() {
-        			let s = handle_contact_event(&mut self.player, &mut self.coin, &mut self.vending, &self.world, &self.assets, event, _ctx);
-
-        			self.score = self.score + s;
-        			
-                    if self.vending.isPickedUp() {
-                        self.win_bool = true;
-                    }
+        self.hot_reload(_ctx);
 
-    			}
+    	while timer::check_update_time( _ctx, DESIRED_FPS) {
+            // The title and end screens keep draining the update timer so that
+            // a run doesn't start with a backlog of physics steps.
+            if self.screen == Screen::Cutscene {
+                self.update_cutscene(_ctx, 1. / DESIRED_FPS as f64);
+                continue;
+            }
+            if self.screen != Screen::Playing {
+                continue;
+            }
 
-    		}
+            // The time scale decides how many game steps each update runs,
+            // with fractions of a step carried over to the next update
+            self.pending_steps += self.time_scale;
+            while self.pending_steps >= 1. && self.screen == Screen::Playing {
+                self.pending_steps -= 1.;
+                self.step();
+            }
 		}	
         Ok(())
     }
@@ -382,96 +2046,98 @@ impl event::EventHandler for MainState {
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx);
 
-        // Update Scoreboard
-        if format!("Score: {}", self.score) != self.score_display.contents() {
-            self.update_ui(ctx);
+        // The title, options, leaderboard and world map screens are drawn over the background alone.
+        let menu_screens = [Screen::Title, Screen::Options, Screen::Leaderboard, Screen::WorldMap];
+        if menu_screens.contains(&self.screen) {
+            graphics::draw(ctx, &self.image1, graphics::Point2::new(0.0, 0.0), 0.0)?;
+            match self.screen {
+                Screen::Title => self.draw_title(ctx)?,
+                Screen::Options => self.options_menu.draw(ctx)?,
+                Screen::WorldMap => self.draw_world_map(ctx)?,
+                _ => self.draw_leaderboard(ctx, 200.)?,
+            }
+            graphics::present(ctx);
+            return Ok(());
         }
 
-        let coords = (self.screen_width, self.screen_height);
+        let coords = &self.camera;
 
         let assets = &mut self.assets;
         let p = &self.player;
-        let dst = graphics::Point2::new(0.0, 0.0);
+        // The background moves with the camera when a cutscene pans it
+        let dst = coords.world_to_screen(WorldPos(Vector2::new(-WINDOW_WIDTH / 2., WINDOW_HEIGHT / 2.))).point();
         graphics::draw(ctx, &self.image1, dst, 0.0)?;
 
+        for terrain in self.terrain.iter().filter(|t| !t.broken) {
+            draw_terrain(ctx, terrain, coords)?;
+        }
+
+        for volume in self.level.volumes.iter().filter(|v| v.kind.is_climbable()) {
+            draw_volume(ctx, volume, coords)?;
+        }
+
+        for (checkpoint, &reached) in self.level.checkpoints.iter().zip(&self.checkpoints_reached) {
+            draw_checkpoint(ctx, checkpoint, reached, coords)?;
+        }
+
         draw_vending(assets, ctx, &mut self.vending, coords)?;
 
-        if !self.coin.isPickedUp(){
-			draw_coin(assets, ctx, &self.coin, coords)?;
-		}
+        for enemy in &self.enemies {
+            if !enemy.is_defeated() {
+                draw_enemy(assets, ctx, enemy, coords)?;
+            }
+        }
+
+        for coin in &self.coins {
+            if !coin.isPickedUp(){
+                draw_coin(assets, ctx, coin, coords)?;
+            }
+        }
+        let show_ghost = self.settings.show_ghost;
+        if let Some(ghost) = self.best_ghost.as_ref().filter(|_| show_ghost) {
+            if let Some(pos) = ghost.position_at(self.run_timer.ticks()) {
+                draw_ghost(assets, ctx, pos, coords)?;
+            }
+        }
         draw_actor(assets, ctx, p, coords)?;
 
-		let pos = world_to_screen_coords(ctx.conf.window_mode.height, ctx.conf.window_mode.width, Vector2::new(0., 0.));
+        // Water is drawn over the player, so Ferris looks submerged
+        for volume in self.level.volumes.iter().filter(|v| v.kind == VolumeKind::Water) {
+            draw_volume(ctx, volume, coords)?;
+        }
 
-        // Drawables are drawn from their top-left corner.
-        let dest_point = graphics::Point2::new(10.0, 10.0);
-        let score_point = graphics::Point2::new(10.0, 80.0);
+        self.draw_hud(ctx)?;
 
-        graphics::draw_ex(
-                ctx,
-                &self.text,
-                graphics::DrawParam {
-                    dest: graphics::Point2::new(dest_point.x +3., dest_point.y + 3.),
-                    color: Some(graphics::Color::from((146, 32, 27, 255))),
-                    ..Default::default()
-                },
-            )?;
-        graphics::draw_ex(
-                ctx,
-                &self.text,
-                graphics::DrawParam {
-                    dest: dest_point,
-                    color: Some(graphics::Color::from((228, 55, 23, 255))),
-                    ..Default::default()
-                },
-            )?;
-
-        graphics::draw_ex(
-                ctx,
-                &self.score_display,
-                graphics::DrawParam {
-                	dest: graphics::Point2::new(score_point.x + 3., score_point.y + 3.),
-                    color: Some(graphics::Color::from((146, 32, 27, 255))),
-                    ..Default::default()
-                },
-            )?;
-
-        graphics::draw_ex(
-                ctx,
-                &self.score_display,
-                graphics::DrawParam {
-                    dest: score_point,
-                    color: Some(graphics::Color::from((228, 55, 23, 255))),
-                    ..Default::default()
-                },
-            )?;
+        if self.screen == Screen::Shop {
+            self.draw_shop(ctx)?;
+        }
 
-        if self.win_bool {
-            let mut height = 0.0;
-            let background_text = &self.win_display;
+        if self.screen == Screen::EnterInitials {
+            self.draw_initials_prompt(ctx)?;
+        }
 
-            for (_key, text) in background_text {
-                let h = text.height(ctx) as f32;
-                let w = text.width(ctx) as f32;
-                text.queue(ctx, Point2::new(1920./2. - w / 2. +3.,1080./2. - h + height+3. ), Some(graphics::Color::from((0, 0, 0, 255))));
-                height += 50. + h;
-            }
-            height = 0.0;
-            for (_key, text) in &self.win_display {
-                let h = text.height(ctx) as f32;
-                let w = text.width(ctx) as f32;
-                text.queue(ctx, Point2::new(1920./2. - w / 2., 1080./2. - h + height), Some(graphics::Color::from((185, 30, 1, 255))));
-                height += 50. + h;
-            }
+        if self.screen == Screen::Editing {
+            self.draw_editor(ctx)?;
+        }
+
+        if self.screen == Screen::Cutscene {
+            self.draw_cutscene(ctx)?;
+        }
+
+        if self.console.is_open() {
+            self.draw_console(ctx)?;
+        }
 
-            TextCached::draw_queued(ctx, DrawParam::default())?;
+        if self.screen == Screen::Won {
+            self.win_display.queue(ctx)?;
+            self.draw_leaderboard(ctx, 600.)?;
         }
 
         graphics::present(ctx);
 
         self.frames += 1;
         if (self.frames % 100) == 0 {
-            println!("FPS: {}", ggez::timer::get_fps(ctx));
+            debug!("FPS: {}", ggez::timer::get_fps(ctx));
         }
         Ok(())
     }
@@ -481,7 +2147,114 @@ impl event::EventHandler for MainState {
     /// character interactions.
     #[inline]
     fn key_down_event(&mut self, ctx: &mut Context, keycode: Keycode, _keymod: Mod, _repeat: bool) {
+        // The developer console takes every key while it is open
+        if keycode == Keycode::Backquote && self.screen == Screen::Playing {
+            self.console.toggle();
+            return;
+        }
+        if self.console.is_open() {
+            match keycode {
+                Keycode::Return => {
+                    let output = match self.console.submit() {
+                        Some(Ok(command)) => self.run_command(ctx, command),
+                        Some(Err(e)) => e,
+                        None => String::new(),
+                    };
+                    self.console.print(&output);
+                }
+                Keycode::Backspace => self.console.backspace(),
+                Keycode::Escape => self.console.toggle(),
+                _ => {},
+            }
+            return;
+        }
+
+        match self.screen {
+            Screen::Title | Screen::Options | Screen::Leaderboard | Screen::WorldMap | Screen::Shop | Screen::Won => {
+                if let Some(input) = UiInput::from_keycode(keycode) {
+                    self.menu_input(ctx, input);
+                }
+                return;
+            }
+            Screen::EnterInitials => {
+                match keycode {
+                    Keycode::Return if !self.initials.is_empty() => self.submit_initials(ctx),
+                    Keycode::Backspace => { self.initials.pop(); },
+                    // Skipping the initials still records the run
+                    Keycode::Escape => {
+                        self.initials.clear();
+                        self.submit_initials(ctx);
+                    }
+                    _ => {
+                        let name = keycode.name();
+                        if name.len() == 1 && self.initials.len() < INITIALS_LEN {
+                            let letter = name.chars().next().unwrap();
+                            if letter.is_ascii_alphabetic() {
+                                self.initials.push(letter.to_ascii_uppercase());
+                            }
+                        }
+                    },
+                }
+                return;
+            }
+            // Only the dialogue keys work while a cutscene plays
+            Screen::Cutscene => {
+                if let Some(ref mut cutscene) = self.cutscene {
+                    match keycode {
+                        Keycode::Return | Keycode::Space => cutscene.advance(),
+                        Keycode::Up => cutscene.select(-1),
+                        Keycode::Down => cutscene.select(1),
+                        Keycode::Escape => ctx.quit().unwrap(),
+                        _ => {},
+                    }
+                }
+                return;
+            }
+            Screen::Playing => {},
+            Screen::Editing => {
+                let ctrl = _keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+                let shift = _keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                let edited = match keycode {
+                    Keycode::F2 | Keycode::Escape => {
+                        self.toggle_editor();
+                        false
+                    }
+                    Keycode::Num1 => { self.editor.set_tool(Tool::Ground); false }
+                    // Picking the coin tool again changes the kind of coin placed
+                    Keycode::Num2 => {
+                        let kind = match self.editor.tool() {
+                            Tool::Coin(CoinKind::Bronze) => CoinKind::Silver,
+                            Tool::Coin(CoinKind::Silver) => CoinKind::Gold,
+                            _ => CoinKind::Bronze,
+                        };
+                        self.editor.set_tool(Tool::Coin(kind));
+                        false
+                    }
+                    Keycode::Num3 => { self.editor.set_tool(Tool::Goal); false }
+                    Keycode::Num4 => { self.editor.set_tool(Tool::Spawn); false }
+                    Keycode::Z if ctrl && shift => self.editor.redo(&mut self.level),
+                    Keycode::Z if ctrl => self.editor.undo(&mut self.level),
+                    Keycode::Y if ctrl => self.editor.redo(&mut self.level),
+                    Keycode::S if ctrl => {
+                        self.save_level(ctx);
+                        false
+                    }
+                    _ => false,
+                };
+                if edited {
+                    self.level_edited();
+                }
+                return;
+            }
+        }
+        if keycode == Keycode::F2 && self.screen == Screen::Playing {
+            self.toggle_editor();
+            return;
+        }
+
     	if let Some(event) = self.gameInput.key_down_event(keycode) {
+    		// The run starts with the first move, jump or dash
+    		self.run_timer.start();
     		match keycode {
 	    		Keycode::Right => {
 	    			self.player.input(InputEvent::UpdateMovement(Some(Direction::Right)));
@@ -491,12 +2264,24 @@ impl event::EventHandler for MainState {
 	    		}
 	    		Keycode::Space => {
 
-	    			if self.player.grounded {
-    					let _ = self.assets.jump.play();
+	    			if self.gameInput.down_held() && self.player.on_platform() {
+	    				self.player.input(InputEvent::DropThrough);
+	    			}
+	    			else {
+	    				if self.player.can_jump() {
+    						let _ = self.assets.jump.play();
+    						self.telemetry.record(self.run_timer.time(), TelemetryEvent::Jump { pos: self.player.pos });
+	    				}
+	    				self.player.input(InputEvent::PressJump);
 	    			}
-	    			self.player.input(InputEvent::PressJump);
 
 	    		}
+	    		Keycode::LShift | Keycode::X => {
+	    			self.player.input(InputEvent::Dash);
+	    		}
+	    		Keycode::Up | Keycode::Down => {
+	    			self.player.input(event);
+	    		}
 	    		Keycode::Escape => ctx.quit().unwrap(),
 	    		_ => {},
     		}
@@ -506,19 +2291,77 @@ impl event::EventHandler for MainState {
     /// A function used to handle the finishing of a key being pressed down. This will help with
     /// game physics impacts on the main player character.
 	#[inline]
+    // Releases reach the player whatever screen is showing, so a direction held when
+    // a menu opened doesn't stay pressed after it closes.
     fn key_up_event(&mut self, ctx:&mut Context, keycode: Keycode, _keymod: Mod, _repeat: bool) {
     	if let Some(event) = self.gameInput.key_up_event(keycode) {
 	    	match keycode {
-	    		Keycode::Right => {
-	    			self.player.input(InputEvent::UpdateMovement(None));
-	    		}
-	    		Keycode::Left => {
-	    			self.player.input(InputEvent::UpdateMovement(None));
+	    		Keycode::Right | Keycode::Left | Keycode::Up | Keycode::Down => {
+	    			self.player.input(event);
 	    		}
 	    		_ => {},
 	    	}
     	}
     }
+
+    fn mouse_button_down_event(&mut self, _ctx: &mut Context, button: event::MouseButton, x: i32, y: i32) {
+        if self.screen != Screen::Editing {
+            return;
+        }
+        let pos = self.camera.screen_to_world(ScreenPos(Vector2::new(x as f32, y as f32))).0;
+        let edited = match button {
+            event::MouseButton::Left => self.editor.press(&mut self.level, pos),
+            event::MouseButton::Right => self.editor.delete(&mut self.level, pos),
+            _ => false,
+        };
+        if edited {
+            self.level_edited();
+        }
+    }
+
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: event::MouseButton, _x: i32, _y: i32) {
+        if self.screen == Screen::Editing && button == event::MouseButton::Left && self.editor.release(&mut self.level) {
+            self.level_edited();
+        }
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, _state: event::MouseState, x: i32, y: i32, _xrel: i32, _yrel: i32) {
+        self.mouse = self.camera.screen_to_world(ScreenPos(Vector2::new(x as f32, y as f32))).0;
+        if self.screen == Screen::Editing {
+            if let Some(item) = self.editor.motion(&mut self.level, self.mouse) {
+                self.item_moved(item);
+            }
+        }
+    }
+
+    /// Typed text goes to the developer console while it is open.
+    fn text_input_event(&mut self, _ctx: &mut Context, text: String) {
+        if self.console.is_open() {
+            self.console.type_text(&text);
+        }
+    }
+
+    /// A gamepad's d-pad and face buttons work the menus.
+    fn controller_button_down_event(&mut self, ctx: &mut Context, button: event::Button, _instance_id: i32) {
+        if let Some(input) = UiInput::from_button(button) {
+            self.menu_input(ctx, input);
+        }
+    }
+}
+
+/// `generate_level()` generates the level for `seed` with the stats of the
+/// tuning profile it will be played with, and writes it to the user data
+/// directory. Returns the path to load it from.
+fn generate_level(ctx: &mut Context, seed: u64, tuning_profile: Option<&str>) -> GameResult<String> {
+    let tuning = TuningProfiles::load(ctx)?;
+    let (name, stats) = tuning.select(&[tuning_profile]);
+    let mut level = generator::generate(seed, &stats).map_err(GameError::ResourceLoadError)?;
+    level.tuning = Some(name.to_string());
+    let path = format!("/levels/{}.lvl", level.name);
+    let mut file = ctx.filesystem.create(&path)?;
+    file.write_all(level.serialize().as_bytes())?;
+    info!("Generated {} from seed {}", path, seed);
+    Ok(path)
 }
 
 /// Now our main function, which does three things:
@@ -530,6 +2373,49 @@ impl event::EventHandler for MainState {
 /// do the work of creating our MainState and running our game.
 /// * Then, just call `game.run()` which runs the `Game` mainloop.
 pub fn main() {
+    // `--tuning <profile>` plays every level with the given tuning profile,
+    // `--log <spec>` filters the log, `--telemetry` records gameplay events,
+    // `--generate <seed>` plays a level generated from the seed, and
+    // `--language <code>` switches the game to another language.
+    let mut tuning_profile = None;
+    let mut language = None;
+    let mut generate_seed = None;
+    let mut log_spec = env::var(logging::LOG_ENV).unwrap_or_else(|_| logging::DEFAULT_SPEC.to_string());
+    let mut record_telemetry = false;
+    let mut arg_errors = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tuning" => match args.next() {
+                Some(name) => tuning_profile = Some(name),
+                None => arg_errors.push("--tuning expects the name of a tuning profile".to_string()),
+            },
+            "--log" => match args.next() {
+                Some(spec) => log_spec = spec,
+                None => arg_errors.push("--log expects a log filter".to_string()),
+            },
+            "--telemetry" => record_telemetry = true,
+            "--language" => match args.next() {
+                Some(code) => language = Some(code),
+                None => arg_errors.push("--language expects a language code".to_string()),
+            },
+            "--generate" => match args.next().map(|seed| seed.parse::<u64>()) {
+                Some(Ok(seed)) => generate_seed = Some(seed),
+                _ => arg_errors.push("--generate expects a number to seed the level with".to_string()),
+            },
+            _ => arg_errors.push(format!("Unknown argument `{}`", arg)),
+        }
+    }
+
+    let logger = Logger::parse(&log_spec).unwrap_or_else(|e| {
+        arg_errors.push(format!("Invalid log filter: {}", e));
+        Logger::parse(logging::DEFAULT_SPEC).unwrap()
+    });
+    logger.init().expect("the logger is only set once");
+    for e in arg_errors {
+        warn!("{}", e);
+    }
+
     let mut cb = ContextBuilder::new("Hello Ferris", "ggez")
     	.window_setup(conf::WindowSetup::default().title("Ferris and the Safe World!"))
         .window_mode(conf::WindowMode{
@@ -546,43 +2432,43 @@ pub fn main() {
 
     // We add the CARGO_MANIFEST_DIR/resources to the filesystem's path
     // so that ggez will look in our cargo project directory for files.
+    // Edits to the files there are reloaded into the running game.
+    let mut resources = None;
     if let Ok(manifest_dir) = env::var("CARGO_MANIFEST_DIR") {
         let mut path = path::PathBuf::from(manifest_dir);
         path.push("resources");
-        cb = cb.add_resource_path(path);
+        cb = cb.add_resource_path(path.clone());
+        resources = Some(path);
     }
 
     let ctx = &mut cb.build().unwrap();
 
-    let mut state = MainState::new(ctx).unwrap();
-
-    // Create the object shapes to use for our collision handles
-    let ground = ShapeHandle2::new(Cuboid2::new(Vector2::new(1920., 32.)));
-    let playerShape = ShapeHandle2::new(Cuboid2::new(Vector2::new(220., 160. )));
-    let coinShape = ShapeHandle2::new(Cuboid2::new(Vector2::new(0.1, 0.1)));
-    let vendShape = ShapeHandle2::new(Cuboid2::new(Vector2::new(200., 450.)));
-	let groups = CollisionGroups::new();
-	let query = GeometricQueryType::Contacts(0., 0.);
-	let pos = world_to_screen_coords(ctx.conf.window_mode.height, ctx.conf.window_mode.width, Vector2::new(0., -500.));
-    // Add the ground collision object
-	state.add_collision_entity(Isometry2::new(Vector2::new(pos.x -(WINDOW_WIDTH /2.), pos.y), 0.), ground.clone(), groups, query);
-    
-    let pos = world_to_screen_coords(ctx.conf.window_mode.height, ctx.conf.window_mode.width, Vector2::new(0., 0.));
-    // Set the player, coin, and vending machine collision handles
-	let player_collision_handle = state.add_collision_entity(Isometry2::new(Vector2::new(pos.x, pos.y), 0.), playerShape.clone(), groups, query);
-	let coin_collision_handle = state.add_collision_entity(Isometry2::new(Vector2::new(pos.x, pos.y), 0.), coinShape.clone(), groups, query);
-    let vending_collision_handle = state.add_collision_entity(Isometry2::new(Vector2::new(pos.x, pos.y), 0.), vendShape.clone(), groups, query);
-
-    // Add the collision handles to their respective player, coin, and vending machine `Actor` objects.
-    state.player.set_col_handle(player_collision_handle);	
-    state.coin.set_col_handle(coin_collision_handle);
-    state.vending.set_col_handle(vending_collision_handle);
-
-
+    let level_path = match generate_seed {
+        Some(seed) => generate_level(ctx, seed, tuning_profile.as_ref().map(|n| n.as_str())).unwrap_or_else(|e| {
+            warn!("Unable to generate a level: {}", e);
+            START_LEVEL.to_string()
+        }),
+        None => START_LEVEL.to_string(),
+    };
+    let mut state = MainState::new(ctx, &level_path).unwrap();
+    if let Some(resources) = resources {
+        state.watch_resources(resources);
+    }
+    if let Some(name) = tuning_profile {
+        state.override_tuning(name);
+    }
+    if record_telemetry {
+        state.enable_telemetry(ctx);
+    }
+    if let Some(code) = language {
+        if let Err(e) = state.set_language(ctx, &code) {
+            warn!("Unable to switch the language to {}: {}", code, e);
+        }
+    }
 
     if let Err(e) = event::run(ctx, &mut state) {
-        println!("Error encountered: {}", e);
+        error!("Error encountered: {}", e);
     } else {
-        println!("Game exited cleanly.");
+        info!("Game exited cleanly.");
     }
 }
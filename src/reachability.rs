@@ -0,0 +1,336 @@
+//! `reachability` works out which parts of a level the player can get to
+//! from the spawn point, to catch levels that can't be finished before they
+//! ship. The player's jump is traced with `Player::step()` itself, so the
+//! analysis follows the movement stats and abilities the level is played
+//! with.
+//!
+//! The analysis starts with the fall from the spawn point, and every ground
+//! block, slope and volume the player can land on or climb into is jumped
+//! from in turn. A coin is reachable if the player's body can touch it from
+//! anywhere reachable, and the goal if the player can touch the vending
+//! machine. Ceilings, walls, enemies and wall jumps aren't taken into
+//! account, so a level the analysis passes may still be harder than it
+//! thinks, but one it fails can't be finished.
+
+use ggez::graphics::Vector2;
+
+use actors::object::{Object, SENSOR_HALF_EXTENTS};
+use actors::player::{Abilities, Player, TerrainContact};
+use actors::status::PlayerStats;
+use game_inputs::{Direction, InputEvent};
+use level::Level;
+
+/// How far below its take off point a jump is followed.
+const MAX_DROP: f32 = 2000.;
+
+/// `JumpArc` is the path of the player's highest jump while running at full
+/// speed, one point per step relative to where it started. Air jumps are
+/// taken at the top of the arc.
+#[derive(Clone, Debug)]
+pub struct JumpArc {
+    points: Vec<Vector2>,
+}
+
+impl JumpArc {
+    /// The arc of a jump from the ground.
+    pub fn new(stats: &PlayerStats, abilities: Abilities) -> JumpArc {
+        JumpArc::trace(stats, abilities, true)
+    }
+
+    /// The arc of a fall that starts in the air, such as from the spawn point.
+    pub fn fall(stats: &PlayerStats, abilities: Abilities) -> JumpArc {
+        JumpArc::trace(stats, abilities, false)
+    }
+
+    fn trace(stats: &PlayerStats, abilities: Abilities, jump: bool) -> JumpArc {
+        let mut player = Player::new(Vector2::new(0., 0.), 0., Some(Direction::Right));
+        player.set_abilities(abilities);
+        player.set_base_stats(*stats);
+        if jump {
+            player.set_terrain_contact(TerrainContact { ground: true, ..TerrainContact::default() });
+        }
+        player.velocity.x = stats.max_move_speed as f32;
+        let mut points = vec![player.pos];
+        if jump {
+            player.input(InputEvent::PressJump);
+            points.push(player.pos);
+        }
+        while player.pos.y > -MAX_DROP {
+            if player.velocity.y <= 0. && player.can_jump() {
+                player.input(InputEvent::PressJump);
+            }
+            player.step();
+            points.push(player.pos);
+        }
+        JumpArc { points }
+    }
+
+    /// How high above the take off point the arc reaches.
+    pub fn height(&self) -> f32 {
+        self.points.iter().fold(0., |h, p| h.max(p.y))
+    }
+
+    /// `reach()` is the furthest the player can travel sideways and still
+    /// land on a surface `dy` above its take off point (below if negative),
+    /// or `None` if the surface is higher than the arc.
+    pub fn reach(&self, dy: f32) -> Option<f32> {
+        let height = self.height();
+        if dy > height {
+            return None;
+        }
+        let apex = self.points.iter().position(|p| p.y == height).unwrap_or(0);
+        self.points[apex..].iter()
+            .take_while(|p| p.y >= dy)
+            .last()
+            .map(|p| p.x)
+    }
+}
+
+/// `Reachability` is what the player can get to in a level, with an entry
+/// for each of the level's ground blocks, slopes, volumes and coins.
+#[derive(Clone, Debug)]
+pub struct Reachability {
+    pub ground: Vec<bool>,
+    pub slopes: Vec<bool>,
+    pub volumes: Vec<bool>,
+    pub coins: Vec<bool>,
+    pub goal: bool,
+}
+
+impl Reachability {
+    /// A level is solvable if the goal and every coin can be reached.
+    pub fn is_solvable(&self) -> bool {
+        self.goal && self.coins.iter().all(|&reached| reached)
+    }
+
+    /// `problems()` describes each coin that can't be collected, and the goal
+    /// if it can't be reached.
+    pub fn problems(&self, level: &Level) -> Vec<String> {
+        let mut problems: Vec<String> = self.coins.iter().enumerate()
+            .filter(|&(_, &reached)| !reached)
+            .map(|(i, _)| {
+                let coin = &level.coins[i];
+                format!("the {} coin at {} {} can't be reached", coin.kind.name(), coin.pos.x, coin.pos.y)
+            })
+            .collect();
+        if !self.goal {
+            problems.push(format!("the goal at {} {} can't be reached", level.goal.x, level.goal.y));
+        }
+        problems
+    }
+
+    /// The number of ground blocks, slopes and volumes that can't be reached.
+    /// These aren't problems, as scenery may be out of reach on purpose.
+    pub fn unreachable_terrain(&self) -> usize {
+        self.ground.iter().chain(&self.slopes).chain(&self.volumes).filter(|&&reached| !reached).count()
+    }
+}
+
+/// An axis aligned box, as its left, right, bottom and top.
+#[derive(Clone, Copy, Debug)]
+struct Bounds {
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+}
+
+impl Bounds {
+    fn around(center: Vector2, half_extents: Vector2) -> Bounds {
+        Bounds {
+            left: center.x - half_extents.x,
+            right: center.x + half_extents.x,
+            bottom: center.y - half_extents.y,
+            top: center.y + half_extents.y,
+        }
+    }
+
+    fn overlaps(&self, other: &Bounds) -> bool {
+        self.left <= other.right && other.left <= self.right
+            && self.bottom <= other.top && other.bottom <= self.top
+    }
+
+    fn contains(&self, point: Vector2) -> bool {
+        self.overlaps(&Bounds { left: point.x, right: point.x, bottom: point.y, top: point.y })
+    }
+}
+
+/// Something the player lands on. Slopes are landed on at their lower end
+/// and can be walked up to their higher end.
+#[derive(Clone, Copy, Debug)]
+struct Surface {
+    left: f32,
+    right: f32,
+    land: f32,
+    stand: f32,
+}
+
+/// Where the player can take off from: the range of positions for the left
+/// of its body, with its feet at `feet`.
+#[derive(Clone, Copy, Debug)]
+struct Footing {
+    left: f32,
+    right: f32,
+    feet: f32,
+}
+
+/// `analyze()` finds what the player, moving with `stats` and the level's
+/// abilities, can reach from the spawn point.
+pub fn analyze(level: &Level, stats: &PlayerStats) -> Reachability {
+    let jump = JumpArc::new(stats, level.abilities);
+    let fall = JumpArc::fall(stats, level.abilities);
+    let mut player = Player::new(level.spawn, 0., None);
+    player.set_base_stats(*stats);
+    let size = player.half_extents() * 2.;
+
+    let surfaces: Vec<Surface> = level.ground.iter()
+        .map(|block| {
+            let bounds = Bounds::around(block.pos, block.half_extents);
+            Surface { left: bounds.left, right: bounds.right, land: bounds.top, stand: bounds.top }
+        })
+        .chain(level.slopes.iter().map(|slope| Surface {
+            left: slope.start.x.min(slope.end.x),
+            right: slope.start.x.max(slope.end.x),
+            land: slope.start.y.min(slope.end.y),
+            stand: slope.start.y.max(slope.end.y),
+        }))
+        .collect();
+    let volumes: Vec<Bounds> = level.volumes.iter().map(|v| Bounds::around(v.pos, v.half_extents)).collect();
+    let (sensor_hw, sensor_hh) = SENSOR_HALF_EXTENTS;
+    let goal = Bounds::around(Object::sensor_center(level.goal), Vector2::new(sensor_hw, sensor_hh));
+
+    let mut landed = vec![false; surfaces.len()];
+    let mut entered = vec![false; volumes.len()];
+    let mut coins = vec![false; level.coins.len()];
+    let mut goal_reached = false;
+
+    // The player's feet are at the bottom of its body, `pos` being the top left
+    let spawn = Footing { left: level.spawn.x, right: level.spawn.x, feet: level.spawn.y - size.y };
+    let mut queue = vec![(spawn, &fall)];
+    while let Some((footing, arc)) = queue.pop() {
+        let mut previous: Option<Vector2> = None;
+        for point in &arc.points {
+            let feet = footing.feet + point.y;
+            let (left, right) = (footing.left - point.x, footing.right + point.x);
+            let reach = Bounds { left, right: right + size.x, bottom: feet, top: feet + size.y };
+
+            // Land on the surfaces the feet pass down through
+            if let Some(previous) = previous.filter(|p| p.y > point.y) {
+                for (i, surface) in surfaces.iter().enumerate() {
+                    let passed = footing.feet + previous.y >= surface.land && feet < surface.land;
+                    if !landed[i] && passed && left < surface.right && right + size.x > surface.left {
+                        landed[i] = true;
+                        queue.push((Footing { left: surface.left - size.x, right: surface.right, feet: surface.stand }, &jump));
+                    }
+                }
+            }
+            // Climb or swim to the top of the volumes touched
+            for (i, volume) in volumes.iter().enumerate() {
+                if !entered[i] && reach.overlaps(volume) {
+                    entered[i] = true;
+                    queue.push((Footing { left: volume.left - size.x, right: volume.right, feet: volume.top }, &jump));
+                }
+            }
+            for (i, coin) in level.coins.iter().enumerate() {
+                coins[i] = coins[i] || reach.contains(coin.pos);
+            }
+            goal_reached = goal_reached || reach.overlaps(&goal);
+            previous = Some(*point);
+        }
+    }
+
+    // Anything inside a volume can be reached by climbing or swimming to it
+    for (i, coin) in level.coins.iter().enumerate() {
+        coins[i] = coins[i] || volumes.iter().zip(&entered).any(|(volume, &entered)| {
+            entered && Bounds {
+                left: volume.left - size.x,
+                right: volume.right + size.x,
+                bottom: volume.bottom - size.y,
+                top: volume.top + size.y,
+            }.contains(coin.pos)
+        });
+    }
+
+    let slopes = landed.split_off(level.ground.len());
+    Reachability { ground: landed, slopes, volumes: entered, coins, goal: goal_reached }
+}
+
+/// `assert_solvable()` panics, listing the problems, if every coin and the
+/// goal of `level` can't be reached. It is meant for tests.
+pub fn assert_solvable(level: &Level, stats: &PlayerStats) {
+    let reachability = analyze(level, stats);
+    assert!(reachability.is_solvable(), "level `{}` can't be finished:\n{}",
+        level.name, reachability.problems(level).join("\n"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actors::player::base_stats;
+
+    fn level(items: &str) -> Level {
+        Level::parse(&format!("spawn 0 0\nground 0 -500 960 32\n{}", items)).unwrap()
+    }
+
+    #[test]
+    fn double_jump_reaches_higher() {
+        let stats = base_stats();
+        let single = JumpArc::new(&stats, Abilities::default());
+        let double = JumpArc::new(&stats, Abilities { double_jump: true, ..Abilities::default() });
+        assert!(single.height() > 0.);
+        assert!(double.height() > single.height());
+    }
+
+    #[test]
+    fn reach_shrinks_with_height() {
+        let arc = JumpArc::new(&base_stats(), Abilities::default());
+        let low = arc.reach(0.).unwrap();
+        let high = arc.reach(arc.height() / 2.).unwrap();
+        assert!(low > high && high > 0.);
+        assert_eq!(arc.reach(arc.height() + 1.), None);
+    }
+
+    /// The height above the ground's top a coin can't be jumped to.
+    fn out_of_reach() -> f32 {
+        let stats = base_stats();
+        let size = Player::new(Vector2::new(0., 0.), 0., None).half_extents() * 2.;
+        -468. + JumpArc::new(&stats, Abilities::default()).height() + size.y + 100.
+    }
+
+    #[test]
+    fn coins_and_goal_on_the_ground_are_reachable() {
+        let level = level("coin -400 -440 bronze\ncoin 400 -440 gold\ngoal 600 -468\n");
+        let reachability = analyze(&level, &base_stats());
+        assert!(reachability.is_solvable(), "{:?}", reachability.problems(&level));
+        assert_eq!(reachability.ground, vec![true]);
+    }
+
+    #[test]
+    fn coin_above_the_jump_is_unreachable() {
+        let y = out_of_reach();
+        let level = level(&format!("coin 0 {} gold\ngoal 600 -468\n", y));
+        let reachability = analyze(&level, &base_stats());
+        assert_eq!(reachability.coins, vec![false]);
+        assert!(reachability.goal);
+        assert_eq!(reachability.problems(&level), vec![format!("the gold coin at 0 {} can't be reached", y)]);
+    }
+
+    #[test]
+    fn ladder_reaches_a_high_coin() {
+        let y = out_of_reach();
+        let level = level(&format!("ladder 0 {} 16 {}\ncoin 0 {} gold\ngoal 600 -468\n", y / 2. - 234., y / 2. + 234. + 16., y));
+        let reachability = analyze(&level, &base_stats());
+        assert_eq!(reachability.volumes, vec![true]);
+        assert!(reachability.is_solvable(), "{:?}", reachability.problems(&level));
+    }
+
+    #[test]
+    fn out_of_reach_island_is_reported() {
+        let x = JumpArc::new(&base_stats(), Abilities::default()).reach(0.).unwrap() + 3000.;
+        let level = level(&format!("ground {} -500 100 32\ngoal {} -468\n", x, x));
+        let reachability = analyze(&level, &base_stats());
+        assert_eq!(reachability.ground, vec![true, false]);
+        assert!(!reachability.goal);
+        assert_eq!(reachability.unreachable_terrain(), 1);
+    }
+}
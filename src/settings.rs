@@ -0,0 +1,107 @@
+//! `settings` holds the choices made in the options menu. They are saved in
+//! the ggez user directory whenever they change, one `<name> <value>` line
+//! each, and loaded when the game starts:
+//!
+//! ```text
+//! music 0.7
+//! sounds 1
+//! ghost off
+//! ```
+
+use std::io::{Read, Write};
+
+use ggez::{Context, GameResult};
+
+const SETTINGS_PATH: &str = "/settings.txt";
+
+/// `Settings` are the choices made in the options menu.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    /// The volume of the music, from 0 to 1.
+    pub music_volume: f32,
+    /// The volume of the sound effects, from 0 to 1.
+    pub sound_volume: f32,
+    /// Whether the best run's ghost is shown while playing.
+    pub show_ghost: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings { music_volume: 1., sound_volume: 1., show_ghost: true }
+    }
+}
+
+impl Settings {
+    /// Loads the settings saved last time. A missing or unreadable file
+    /// leaves every setting at its default.
+    pub fn load(ctx: &mut Context) -> Settings {
+        let mut contents = String::new();
+        if let Ok(mut file) = ctx.filesystem.open(SETTINGS_PATH) {
+            if file.read_to_string(&mut contents).is_err() {
+                contents.clear();
+            }
+        }
+        Settings::parse(&contents)
+    }
+
+    /// Saves the settings, to start with next time.
+    pub fn save(&self, ctx: &mut Context) -> GameResult<()> {
+        let mut file = ctx.filesystem.create(SETTINGS_PATH)?;
+        file.write_all(self.serialize().as_bytes())?;
+        Ok(())
+    }
+
+    /// Parses the format described in the module docs. Settings that are
+    /// missing or can't be read keep their defaults, and volumes are
+    /// clamped to 0 to 1.
+    pub fn parse(contents: &str) -> Settings {
+        let mut settings = Settings::default();
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 2 {
+                continue;
+            }
+            let volume = fields[1].parse::<f32>().ok().map(|volume| volume.max(0.).min(1.));
+            match (fields[0], fields[1], volume) {
+                ("music", _, Some(volume)) => settings.music_volume = volume,
+                ("sounds", _, Some(volume)) => settings.sound_volume = volume,
+                ("ghost", "on", _) => settings.show_ghost = true,
+                ("ghost", "off", _) => settings.show_ghost = false,
+                _ => warn!("Ignoring the setting `{}`", line),
+            }
+        }
+        settings
+    }
+
+    pub fn serialize(&self) -> String {
+        format!("music {}\nsounds {}\nghost {}\n",
+            self.music_volume, self.sound_volume, if self.show_ghost { "on" } else { "off" })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_text() {
+        let settings = Settings { music_volume: 0.7, sound_volume: 0., show_ghost: false };
+        assert_eq!(settings.serialize(), "music 0.7\nsounds 0\nghost off\n");
+        assert_eq!(Settings::parse(&settings.serialize()), settings);
+        assert_eq!(Settings::parse(&Settings::default().serialize()), Settings::default());
+    }
+
+    #[test]
+    fn missing_and_unreadable_settings_keep_their_defaults() {
+        assert_eq!(Settings::parse(""), Settings::default());
+        let settings = Settings::parse("music loud\nsounds 0.5\nghost maybe\nvolume 1\nghost\n");
+        assert_eq!(settings, Settings { sound_volume: 0.5, ..Settings::default() });
+    }
+
+    #[test]
+    fn volumes_are_clamped() {
+        let settings = Settings::parse("music 3\nsounds -1\n");
+        assert_eq!(settings.music_volume, 1.);
+        assert_eq!(settings.sound_volume, 0.);
+    }
+}
@@ -0,0 +1,149 @@
+//! `Shop` is the vending machine's menu. Touching the vending machine opens
+//! it, and the coins Ferris has collected can be spent on drinks that modify
+//! his movement. The safe rusty drink quenches his thirst and ends the level.
+
+use actors::status::{EffectKind, StatusEffect};
+
+/// What buying a `ShopItem` gives the player.
+#[derive(Clone, Copy, Debug)]
+pub enum Purchase {
+    Effect(StatusEffect),
+    /// The drink Ferris came for, which completes the level.
+    Finish,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ShopItem {
+    /// Identifies the item in the string tables.
+    pub key: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub price: i32,
+    pub purchase: Purchase,
+}
+
+/// The drinks stocked by every vending machine.
+fn catalogue() -> Vec<ShopItem> {
+    vec![
+        ShopItem {
+            key: "ferrous_fizz",
+            name: "Ferrous Fizz",
+            description: "Jump once more in mid air. Lasts the whole level.",
+            price: 1500,
+            purchase: Purchase::Effect(StatusEffect::permanent(EffectKind::DoubleJump)),
+        },
+        ShopItem {
+            key: "borrow_brew",
+            name: "Borrow Brew",
+            description: "Run faster for 20 seconds.",
+            price: 600,
+            purchase: Purchase::Effect(StatusEffect::timed(EffectKind::SpeedUp, 20.)),
+        },
+        ShopItem {
+            key: "lifetime_lemonade",
+            name: "Lifetime Lemonade",
+            description: "A shield that absorbs one hit for 30 seconds.",
+            price: 1000,
+            purchase: Purchase::Effect(StatusEffect::timed(EffectKind::Shield, 30.)),
+        },
+        ShopItem {
+            key: "spring_water",
+            name: "Spring Water",
+            description: "Jump higher for 20 seconds.",
+            price: 600,
+            purchase: Purchase::Effect(StatusEffect::timed(EffectKind::HighJump, 20.)),
+        },
+        ShopItem {
+            key: "shrinking_soda",
+            name: "Shrinking Soda",
+            description: "Shrink down to squeeze through gaps for 15 seconds.",
+            price: 400,
+            purchase: Purchase::Effect(StatusEffect::timed(EffectKind::Small, 15.)),
+        },
+        ShopItem {
+            key: "unsafe_espresso",
+            name: "Unsafe Espresso",
+            description: "Nothing can hurt you for 10 seconds.",
+            price: 2000,
+            purchase: Purchase::Effect(StatusEffect::timed(EffectKind::Invincible, 10.)),
+        },
+        ShopItem {
+            key: "safe_rusty_drink",
+            name: "Safe Rusty Drink",
+            description: "Quench Ferris's thirst and finish the level.",
+            price: 0,
+            purchase: Purchase::Finish,
+        },
+    ]
+}
+
+/// `ShopMessage` is what the vending machine last said, shown under the menu.
+#[derive(Clone, Copy, Debug)]
+pub enum ShopMessage {
+    Silent,
+    Greeting,
+    TooExpensive { item: ShopItem, wallet: i32 },
+    Enjoy(ShopItem),
+}
+
+/// `Shop` keeps track of the stock and the currently highlighted item. The
+/// entry after the last item leaves the shop.
+pub struct Shop {
+    items: Vec<ShopItem>,
+    pub selected: usize,
+    pub message: ShopMessage,
+}
+
+impl Shop {
+    pub fn new() -> Shop {
+        Shop {
+            items: catalogue(),
+            selected: 0,
+            message: ShopMessage::Silent,
+        }
+    }
+
+    pub fn items(&self) -> &[ShopItem] {
+        &self.items
+    }
+
+    /// Resets the highlighted entry and message when the shop is opened.
+    pub fn open(&mut self) {
+        self.selected = 0;
+        self.message = ShopMessage::Greeting;
+    }
+
+    /// The number of selectable entries, including "Leave".
+    fn entry_count(&self) -> usize {
+        self.items.len() + 1
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.entry_count();
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = (self.selected + self.entry_count() - 1) % self.entry_count();
+    }
+
+    /// Returns true if the "Leave" entry is highlighted.
+    pub fn leave_selected(&self) -> bool {
+        self.selected == self.items.len()
+    }
+
+    /// Buys the highlighted item, taking its price out of `wallet`. Returns
+    /// `None` and sets the shop message if the item can't be afforded.
+    pub fn buy(&mut self, wallet: &mut i32) -> Option<Purchase> {
+        let item = match self.items.get(self.selected) {
+            Some(item) => *item,
+            None => return None,
+        };
+        if *wallet < item.price {
+            self.message = ShopMessage::TooExpensive { item, wallet: *wallet };
+            return None;
+        }
+        *wallet -= item.price;
+        self.message = ShopMessage::Enjoy(item);
+        Some(item.purchase)
+    }
+}
@@ -0,0 +1,230 @@
+//! `speedrun` contains the run timer, its splits, and the ghost recording of
+//! the best run. The timer counts fixed physics steps rather than reading the
+//! wall clock, so a run takes the same time no matter how fast frames are
+//! drawn or how many update steps are caught up in a single frame.
+
+use std::io::{Read, Write};
+
+use ggez::graphics::Vector2;
+use ggez::{Context, GameResult};
+
+use actors::player::STEP_PERIOD;
+
+/// What a split was recorded for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SplitKind {
+    Coin,
+    Checkpoint,
+    Goal,
+}
+
+impl SplitKind {
+    fn name(self) -> &'static str {
+        match self {
+            SplitKind::Coin => "coin",
+            SplitKind::Checkpoint => "checkpoint",
+            SplitKind::Goal => "goal",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<SplitKind> {
+        match name {
+            "coin" => Some(SplitKind::Coin),
+            "checkpoint" => Some(SplitKind::Checkpoint),
+            "goal" => Some(SplitKind::Goal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Split {
+    pub kind: SplitKind,
+    pub time: f64,
+}
+
+/// `RunTimer` counts the physics steps between the first input of a run and
+/// reaching the goal.
+pub struct RunTimer {
+    ticks: u64,
+    running: bool,
+    finished: bool,
+    splits: Vec<Split>,
+}
+
+impl RunTimer {
+    pub fn new() -> RunTimer {
+        RunTimer {
+            ticks: 0,
+            running: false,
+            finished: false,
+            splits: Vec::new(),
+        }
+    }
+
+    /// Starts the timer. Only the first call of a run has any effect.
+    pub fn start(&mut self) {
+        if !self.running && !self.finished {
+            self.running = true;
+        }
+    }
+
+    /// Advances the timer by one physics step.
+    pub fn tick(&mut self) {
+        if self.running {
+            self.ticks += 1;
+        }
+    }
+
+    pub fn split(&mut self, kind: SplitKind) {
+        if self.running {
+            let time = self.time();
+            self.splits.push(Split { kind, time });
+        }
+    }
+
+    /// Records the goal split and stops the timer.
+    pub fn stop(&mut self) {
+        self.split(SplitKind::Goal);
+        self.running = false;
+        self.finished = true;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// The elapsed run time in seconds.
+    pub fn time(&self) -> f64 {
+        self.ticks as f64 * STEP_PERIOD
+    }
+
+    pub fn splits(&self) -> &[Split] {
+        &self.splits
+    }
+}
+
+/// `Ghost` is the recorded path of a completed run: one player position per
+/// physics step, along with the run's time and splits.
+pub struct Ghost {
+    pub time: f64,
+    pub splits: Vec<Split>,
+    positions: Vec<Vector2>,
+}
+
+impl Ghost {
+    pub fn new() -> Ghost {
+        Ghost {
+            time: 0.,
+            splits: Vec::new(),
+            positions: Vec::new(),
+        }
+    }
+
+    /// Records the player's position for the current step.
+    pub fn record(&mut self, pos: Vector2) {
+        self.positions.push(pos);
+    }
+
+    /// Marks the recording as a completed run.
+    pub fn finish(&mut self, timer: &RunTimer) {
+        self.time = timer.time();
+        self.splits = timer.splits().to_vec();
+    }
+
+    /// The ghost's position at the given step, holding the last position once
+    /// the recording has run out.
+    pub fn position_at(&self, tick: u64) -> Option<Vector2> {
+        if self.positions.is_empty() {
+            return None;
+        }
+        let index = (tick as usize).min(self.positions.len() - 1);
+        Some(self.positions[index])
+    }
+
+    /// The difference between `split` and the best run's split at the same
+    /// index, if the best run has one of the same kind. Runs that took their
+    /// splits in a different order have nothing to compare.
+    pub fn split_delta(&self, index: usize, split: &Split) -> Option<f64> {
+        self.splits.get(index)
+            .filter(|best| best.kind == split.kind)
+            .map(|best| split.time - best.time)
+    }
+
+    fn path(level: &str) -> String {
+        format!("/ghost_{}.txt", level)
+    }
+
+    /// Loads the best run for `level` from the user directory, if one exists.
+    pub fn load(ctx: &mut Context, level: &str) -> Option<Ghost> {
+        let mut contents = String::new();
+        let mut file = ctx.filesystem.open(Ghost::path(level)).ok()?;
+        file.read_to_string(&mut contents).ok()?;
+        Ghost::parse(&contents)
+    }
+
+    pub fn save(&self, ctx: &mut Context, level: &str) -> GameResult<()> {
+        let mut file = ctx.filesystem.create(Ghost::path(level))?;
+        file.write_all(self.serialize().as_bytes())?;
+        Ok(())
+    }
+
+    /// Parses the line based ghost format:
+    /// `time <seconds>`, `split <kind> <seconds>` and `pos <x> <y>`.
+    pub fn parse(contents: &str) -> Option<Ghost> {
+        let mut ghost = Ghost::new();
+        for line in contents.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["time", time] => ghost.time = time.parse().ok()?,
+                ["split", kind, time] => ghost.splits.push(Split {
+                    kind: SplitKind::from_name(kind)?,
+                    time: time.parse().ok()?,
+                }),
+                ["pos", x, y] => ghost.positions.push(Vector2::new(x.parse().ok()?, y.parse().ok()?)),
+                _ => {},
+            }
+        }
+        if ghost.positions.is_empty() { None } else { Some(ghost) }
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = format!("time {}\n", self.time);
+        for split in &self.splits {
+            out.push_str(&format!("split {} {}\n", split.kind.name(), split.time));
+        }
+        for pos in &self.positions {
+            out.push_str(&format!("pos {} {}\n", pos.x, pos.y));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ghost(splits: &[(SplitKind, f64)]) -> Ghost {
+        let mut ghost = Ghost::new();
+        ghost.splits = splits.iter().map(|&(kind, time)| Split { kind, time }).collect();
+        ghost
+    }
+
+    #[test]
+    fn split_delta_compares_splits_of_the_same_kind() {
+        let best = ghost(&[(SplitKind::Coin, 2.), (SplitKind::Checkpoint, 5.)]);
+        assert_eq!(best.split_delta(0, &Split { kind: SplitKind::Coin, time: 2.5 }), Some(0.5));
+        assert_eq!(best.split_delta(1, &Split { kind: SplitKind::Checkpoint, time: 4. }), Some(-1.));
+    }
+
+    #[test]
+    fn split_delta_is_none_when_the_kinds_differ() {
+        let best = ghost(&[(SplitKind::Coin, 2.), (SplitKind::Checkpoint, 5.)]);
+        assert_eq!(best.split_delta(0, &Split { kind: SplitKind::Checkpoint, time: 3. }), None);
+        assert_eq!(best.split_delta(2, &Split { kind: SplitKind::Goal, time: 9. }), None);
+    }
+}
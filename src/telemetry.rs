@@ -0,0 +1,273 @@
+//! `telemetry` records gameplay events for playtest analysis. Recording is
+//! opt-in with `--telemetry`; each session is written to its own JSON-lines
+//! file in the game's user data directory, one event per line:
+//!
+//! ```text
+//! {"t":0.000,"event":"run_start","level":"beach"}
+//! {"t":1.250,"event":"jump","x":-930.0,"y":-289.5}
+//! {"t":4.016,"event":"death","x":102.4,"y":-290.0,"deaths":1}
+//! ```
+//!
+//! `t` is the run time in seconds when the event happened.
+
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ggez::graphics::Vector2;
+use ggez::filesystem::File;
+use ggez::{Context, GameResult};
+
+/// `TelemetryEvent` is a gameplay event worth recording.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TelemetryEvent {
+    RunStart { level: String },
+    Jump { pos: Vector2 },
+    Death { pos: Vector2, deaths: u32 },
+    Pickup { kind: String, pos: Vector2 },
+    Checkpoint { index: usize, pos: Vector2 },
+    /// Where the player is, recorded every game step.
+    Position { pos: Vector2 },
+    Complete { time: f64, score: i32, deaths: u32 },
+}
+
+impl TelemetryEvent {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TelemetryEvent::RunStart { .. } => "run_start",
+            TelemetryEvent::Jump { .. } => "jump",
+            TelemetryEvent::Death { .. } => "death",
+            TelemetryEvent::Pickup { .. } => "pickup",
+            TelemetryEvent::Checkpoint { .. } => "checkpoint",
+            TelemetryEvent::Position { .. } => "position",
+            TelemetryEvent::Complete { .. } => "complete",
+        }
+    }
+
+    /// Formats the event as a single line of JSON, timestamped with `time`.
+    pub fn to_json(&self, time: f64) -> String {
+        let fields = match *self {
+            TelemetryEvent::RunStart { ref level } => format!(r#","level":{}"#, json_string(level)),
+            TelemetryEvent::Jump { pos } | TelemetryEvent::Position { pos } => json_pos(pos),
+            TelemetryEvent::Death { pos, deaths } => format!(r#"{},"deaths":{}"#, json_pos(pos), deaths),
+            TelemetryEvent::Pickup { ref kind, pos } => format!(r#","kind":{}{}"#, json_string(kind), json_pos(pos)),
+            TelemetryEvent::Checkpoint { index, pos } => format!(r#"{},"index":{}"#, json_pos(pos), index),
+            TelemetryEvent::Complete { time, score, deaths } => {
+                format!(r#","time":{:.3},"score":{},"deaths":{}"#, time, score, deaths)
+            }
+        };
+        format!(r#"{{"t":{:.3},"event":"{}"{}}}"#, time, self.name(), fields)
+    }
+
+    /// Parses a line written by `to_json()` back into its time and event.
+    /// Returns `None` for lines that aren't a recognised event.
+    pub fn parse(line: &str) -> Option<(f64, TelemetryEvent)> {
+        let fields = json_fields(line)?;
+        let field = |name: &str| fields.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref v)| v.as_str());
+        let number = |name: &str| field(name).and_then(|v| v.parse::<f64>().ok());
+        let pos = || Some(Vector2::new(number("x")? as f32, number("y")? as f32));
+
+        let time = number("t")?;
+        let event = match field("event")? {
+            "run_start" => TelemetryEvent::RunStart { level: field("level")?.to_string() },
+            "jump" => TelemetryEvent::Jump { pos: pos()? },
+            "death" => TelemetryEvent::Death { pos: pos()?, deaths: number("deaths")? as u32 },
+            "pickup" => TelemetryEvent::Pickup { kind: field("kind")?.to_string(), pos: pos()? },
+            "checkpoint" => TelemetryEvent::Checkpoint { index: number("index")? as usize, pos: pos()? },
+            "position" => TelemetryEvent::Position { pos: pos()? },
+            "complete" => TelemetryEvent::Complete {
+                time: number("time")?,
+                score: number("score")? as i32,
+                deaths: number("deaths")? as u32,
+            },
+            _ => return None,
+        };
+        Some((time, event))
+    }
+}
+
+/// Splits a flat JSON object into its keys and values, with strings
+/// unquoted. Nested objects and arrays aren't supported, as events never
+/// contain them.
+fn json_fields(line: &str) -> Option<Vec<(String, String)>> {
+    let line = line.trim();
+    if !line.starts_with('{') || !line.ends_with('}') {
+        return None;
+    }
+    let mut chars = line[1..line.len() - 1].chars().peekable();
+    let mut fields = Vec::new();
+    loop {
+        skip_whitespace(&mut chars);
+        if chars.peek().is_none() {
+            return Some(fields);
+        }
+        if chars.next()? != '"' {
+            return None;
+        }
+        let key = json_unquote(&mut chars)?;
+        skip_whitespace(&mut chars);
+        if chars.next()? != ':' {
+            return None;
+        }
+        skip_whitespace(&mut chars);
+        let value = if chars.peek() == Some(&'"') {
+            chars.next();
+            json_unquote(&mut chars)?
+        } else {
+            let mut value = String::new();
+            while let Some(&c) = chars.peek() {
+                if c == ',' {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+            value.trim().to_string()
+        };
+        fields.push((key, value));
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(',') => {}
+            None => return Some(fields),
+            _ => return None,
+        }
+    }
+}
+
+fn skip_whitespace<I: Iterator<Item = char>>(chars: &mut ::std::iter::Peekable<I>) {
+    while chars.peek().map_or(false, |c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+/// Reads the rest of a JSON string whose opening quote has been consumed.
+fn json_unquote<I: Iterator<Item = char>>(chars: &mut I) -> Option<String> {
+    let mut s = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(s),
+            '\\' => match chars.next()? {
+                'n' => s.push('\n'),
+                'u' => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    s.push(::std::char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                }
+                c => s.push(c),
+            },
+            c => s.push(c),
+        }
+    }
+}
+
+fn json_pos(pos: Vector2) -> String {
+    format!(r#","x":{:.1},"y":{:.1}"#, pos.x, pos.y)
+}
+
+/// Quotes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `Telemetry` appends events to the session's file. A disabled recorder
+/// ignores every event, so the game can record unconditionally.
+pub struct Telemetry {
+    file: Option<File>,
+}
+
+impl Telemetry {
+    pub fn disabled() -> Telemetry {
+        Telemetry { file: None }
+    }
+
+    /// Starts recording to a new file named after the current time.
+    pub fn start(ctx: &mut Context) -> GameResult<Telemetry> {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let path = format!("/telemetry-{}.jsonl", secs);
+        let file = ctx.filesystem.create(&path)?;
+        info!("Recording telemetry to {}", path);
+        Ok(Telemetry { file: Some(file) })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some()
+    }
+
+    /// Writes `event` at run time `time`. Recording stops if the file can't
+    /// be written to.
+    pub fn record(&mut self, time: f64, event: TelemetryEvent) {
+        let failed = match self.file {
+            Some(ref mut file) => writeln!(file, "{}", event.to_json(time)).is_err(),
+            None => return,
+        };
+        if failed {
+            warn!("Unable to write telemetry, recording stopped");
+            self.file = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(event: TelemetryEvent) {
+        let line = event.to_json(12.5);
+        assert_eq!(TelemetryEvent::parse(&line), Some((12.5, event)), "{}", line);
+    }
+
+    #[test]
+    fn every_event_round_trips() {
+        let pos = Vector2::new(-930., 102.5);
+        round_trip(TelemetryEvent::RunStart { level: String::from("beach") });
+        round_trip(TelemetryEvent::Jump { pos });
+        round_trip(TelemetryEvent::Death { pos, deaths: 3 });
+        round_trip(TelemetryEvent::Pickup { kind: String::from("gold"), pos });
+        round_trip(TelemetryEvent::Checkpoint { index: 1, pos });
+        round_trip(TelemetryEvent::Position { pos });
+        round_trip(TelemetryEvent::Complete { time: 42.125, score: 9001, deaths: 0 });
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        for level in &[r#"say "hi""#, r"back\slash", "a,b:c", "{braces}", "two\nlines", "tab\there", "Strand \u{65e5}"] {
+            round_trip(TelemetryEvent::RunStart { level: level.to_string() });
+        }
+    }
+
+    #[test]
+    fn whitespace_and_field_order_are_ignored() {
+        let line = r#" { "event" : "death" , "deaths" : 2, "y": -290.0, "x": 102.4, "t": 4.016 } "#;
+        let event = TelemetryEvent::Death { pos: Vector2::new(102.4, -290.), deaths: 2 };
+        assert_eq!(TelemetryEvent::parse(line), Some((4.016, event)));
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        let lines = [
+            "",
+            "not json",
+            r#"["t", 1]"#,
+            r#"{"t":1.000,"event":"jump","x":1.0}"#,
+            r#"{"t":1.000,"event":"teleport","x":1.0,"y":2.0}"#,
+            r#"{"t":"soon","event":"jump","x":1.0,"y":2.0}"#,
+            r#"{"t":1.000,"event":"run_start","level":"beach}"#,
+            r#"{"t":1.000,"event":"jump","x":1.0,"y":2.0"#,
+            r#"{"t":1.000 "event":"jump","x":1.0,"y":2.0}"#,
+            r#"{t:1.000,"event":"jump","x":1.0,"y":2.0}"#,
+            r#"{"t":1.000,"event":"run_start","level":"\u12"}"#,
+        ];
+        for line in &lines {
+            assert_eq!(TelemetryEvent::parse(line), None, "{}", line);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! `triggers` contains the sensors for everything the player can pass
+//! through: pickups, the goal and zones of the level. Sensors are added to
+//! the collision world with `GeometricQueryType::Proximity`, so they report
+//! when the player overlaps them without ever pushing it around, and
+//! `Triggers` turns the world's proximity events into enter, stay and exit
+//! events for the game to handle.
+
+use layers::{Layer, LayerMembership};
+use ncollide::events::ProximityEvent;
+use ncollide::query::Proximity;
+use ncollide::world::{CollisionObjectHandle, CollisionWorld2, GeometricQueryType};
+
+/// The query type every sensor is registered with.
+pub fn sensor_query() -> GeometricQueryType<f32> {
+    GeometricQueryType::Proximity(0.)
+}
+
+/// The collision layers of the level's zones.
+pub fn zone_layers() -> LayerMembership {
+    LayerMembership::new(&[Layer::Trigger])
+}
+
+/// What a sensor belongs to. Indices are into the matching list of the game
+/// state.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum Trigger {
+    Coin(usize),
+    Goal,
+    Volume(usize),
+    Checkpoint(usize),
+}
+
+/// `TriggerEvent` is what happened to a trigger during the last world update.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub enum TriggerEvent {
+    /// The player started overlapping the trigger.
+    Enter(Trigger),
+    /// The player is still overlapping the trigger, and was before this update.
+    Stay(Trigger),
+    /// The player stopped overlapping the trigger.
+    Exit(Trigger),
+}
+
+/// `Triggers` keeps track of every sensor in the collision world and which
+/// of them the player is inside of.
+pub struct Triggers {
+    sensors: Vec<(CollisionObjectHandle, Trigger)>,
+    inside: Vec<CollisionObjectHandle>,
+}
+
+impl Triggers {
+    pub fn new() -> Triggers {
+        Triggers {
+            sensors: Vec::new(),
+            inside: Vec::new(),
+        }
+    }
+
+    /// Registers the collision object `handle` as the sensor for `trigger`.
+    pub fn register(&mut self, handle: CollisionObjectHandle, trigger: Trigger) {
+        self.sensors.push((handle, trigger));
+    }
+
+    /// Forgets the sensor `handle`, such as after a pickup has been removed
+    /// from the collision world. No exit event is sent for it.
+    pub fn unregister(&mut self, handle: CollisionObjectHandle) {
+        self.sensors.retain(|&(h, _)| h != handle);
+        self.inside.retain(|&h| h != handle);
+    }
+
+    /// Forgets which sensors the player is inside of. Used when the player's
+    /// collision object is replaced, as the new object gets its own events.
+    pub fn clear_inside(&mut self) {
+        self.inside.clear();
+    }
+
+    fn trigger(&self, handle: CollisionObjectHandle) -> Option<Trigger> {
+        self.sensors.iter().find(|&&(h, _)| h == handle).map(|&(_, trigger)| trigger)
+    }
+
+    /// The triggers the player is currently inside of.
+    pub fn inside<'a>(&'a self) -> impl Iterator<Item = Trigger> + 'a {
+        self.inside.iter().filter_map(move |&h| self.trigger(h))
+    }
+
+    /// `update()` reads the proximity events of the latest world update that
+    /// involve the player, and returns the trigger events they caused: exits
+    /// first, then enters, then a stay for every other trigger still occupied.
+    pub fn update(&mut self, world: &CollisionWorld2<f32, CollisionObjectData>, player: CollisionObjectHandle) -> Vec<TriggerEvent> {
+        let mut entered = Vec::new();
+        let mut exited = Vec::new();
+        for event in world.proximity_events() {
+            let sensor = match self.sensor_for(event, player) {
+                Some(sensor) => sensor,
+                None => continue,
+            };
+            let was_inside = self.inside.contains(&sensor);
+            if event.new_status == Proximity::Intersecting {
+                if !was_inside {
+                    self.inside.push(sensor);
+                    entered.push(sensor);
+                }
+            }
+            else if was_inside {
+                self.inside.retain(|&h| h != sensor);
+                exited.push(sensor);
+            }
+        }
+
+        let mut events = Vec::new();
+        events.extend(exited.iter().filter_map(|&h| self.trigger(h)).map(TriggerEvent::Exit));
+        events.extend(entered.iter().filter_map(|&h| self.trigger(h)).map(TriggerEvent::Enter));
+        events.extend(self.inside.iter()
+            .filter(|&h| !entered.contains(h))
+            .filter_map(|&h| self.trigger(h))
+            .map(TriggerEvent::Stay));
+        events
+    }
+
+    /// The registered sensor in `event`, if the other object in it is the player.
+    fn sensor_for(&self, event: &ProximityEvent, player: CollisionObjectHandle) -> Option<CollisionObjectHandle> {
+        let other = if event.co1 == player {
+            event.co2
+        }
+        else if event.co2 == player {
+            event.co1
+        }
+        else {
+            return None;
+        };
+        self.trigger(other).map(|_| other)
+    }
+}
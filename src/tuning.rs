@@ -0,0 +1,151 @@
+//! `tuning` loads the player's movement constants from named profiles in
+//! `resources/tuning.txt`, so the feel of the game can be changed and
+//! compared without a rebuild. A level picks its profile with a `tuning`
+//! entry, and `--tuning <profile>` on the command line overrides it for
+//! every level.
+//!
+//! Each profile starts with its name in square brackets, followed by one
+//! value per line. Values a profile doesn't give are taken from the
+//! built in defaults in `actors/player.rs`:
+//!
+//! ```text
+//! # accelerations are per second, speeds are per step, times in seconds
+//! [classic]
+//! move_accel 150
+//! stop_accel 350
+//! fall_accel 360
+//! jump_speed 60
+//! max_fall_speed 40
+//! max_move_speed 10
+//! jump_time 0.21
+//! jump_time_per_speed 0.10
+//! ```
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use ggez::{Context, GameError, GameResult};
+
+use actors::player::{base_stats, STEP_PERIOD};
+use actors::status::PlayerStats;
+
+/// Where the movement tuning is loaded from.
+pub const TUNING_PATH: &str = "/tuning.txt";
+
+/// The profile used when neither the level nor the command line picks one.
+/// It is always available, as the built in defaults, even if the tuning file
+/// doesn't define it.
+pub const DEFAULT_PROFILE: &str = "classic";
+
+/// `TuningProfiles` are the named sets of movement stats to choose from.
+#[derive(Clone, Debug)]
+pub struct TuningProfiles {
+    profiles: BTreeMap<String, PlayerStats>,
+}
+
+impl TuningProfiles {
+    /// Only the default profile, with the built in stats.
+    pub fn builtin() -> TuningProfiles {
+        let mut profiles = BTreeMap::new();
+        profiles.insert(DEFAULT_PROFILE.to_string(), base_stats());
+        TuningProfiles { profiles }
+    }
+
+    /// Loads the profiles from `TUNING_PATH`, falling back to the built in
+    /// profile if the file doesn't exist.
+    pub fn load(ctx: &mut Context) -> GameResult<TuningProfiles> {
+        let mut file = match ctx.filesystem.open(TUNING_PATH) {
+            Ok(file) => file,
+            Err(_) => return Ok(TuningProfiles::builtin()),
+        };
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        TuningProfiles::parse(&contents)
+            .map_err(|e| GameError::ResourceLoadError(format!("{}: {}", TUNING_PATH, e)))
+    }
+
+    /// Parses the profiles from the text format described in the module docs.
+    pub fn parse(contents: &str) -> Result<TuningProfiles, String> {
+        let mut tuning = TuningProfiles::builtin();
+        let mut current: Option<String> = None;
+        let mut defined: Vec<String> = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let err = |msg: &str| format!("line {}: {}", number + 1, msg);
+
+            if line.starts_with('[') && line.ends_with(']') {
+                let name = line[1..line.len() - 1].trim().to_string();
+                if name.is_empty() || name.contains(char::is_whitespace) {
+                    return Err(err("profile names must be a single word"));
+                }
+                if defined.contains(&name) {
+                    return Err(err(&format!("profile `{}` is defined twice", name)));
+                }
+                tuning.profiles.insert(name.clone(), base_stats());
+                defined.push(name.clone());
+                current = Some(name);
+                continue;
+            }
+
+            let stats = match current {
+                Some(ref name) => tuning.profiles.get_mut(name).unwrap(),
+                None => return Err(err("values must come after a `[profile]` line")),
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 2 {
+                return Err(err("expected a name and a value"));
+            }
+            let value = fields[1].parse::<f64>().map_err(|_| err(&format!("`{}` is not a number", fields[1])))?;
+            set_value(stats, fields[0], value).map_err(|e| err(&e))?;
+        }
+        Ok(tuning)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PlayerStats> {
+        self.profiles.get(name)
+    }
+
+    pub fn names<'a>(&'a self) -> impl Iterator<Item = &'a str> + 'a {
+        self.profiles.keys().map(|name| name.as_str())
+    }
+
+    /// `select()` picks the profile to play with: the command line's choice,
+    /// then the level's, then the default. A choice that doesn't name a
+    /// profile is reported and skipped.
+    pub fn select(&self, choices: &[Option<&str>]) -> (&str, PlayerStats) {
+        for name in choices.iter().filter_map(|c| *c) {
+            match self.profiles.get_key_value(name) {
+                Some((name, stats)) => return (name.as_str(), *stats),
+                None => warn!("Unknown tuning profile `{}`, expected one of: {}",
+                    name, self.names().collect::<Vec<_>>().join(", ")),
+            }
+        }
+        (DEFAULT_PROFILE, self.profiles[DEFAULT_PROFILE])
+    }
+}
+
+/// Sets the value called `name` in `stats`, converting it from the units
+/// used in the tuning file.
+fn set_value(stats: &mut PlayerStats, name: &str, value: f64) -> Result<(), String> {
+    let positive = |value: f64| if value > 0. {
+        Ok(value)
+    } else {
+        Err(format!("`{}` must be positive", name))
+    };
+    match name {
+        "move_accel" => stats.move_accel = positive(value)? * STEP_PERIOD,
+        "stop_accel" => stats.stop_accel = positive(value)? * STEP_PERIOD,
+        "fall_accel" => stats.fall_accel = positive(value)? * STEP_PERIOD,
+        "jump_speed" => stats.jump_speed = positive(value)?,
+        "max_fall_speed" => stats.max_fall_speed = positive(value)?,
+        "max_move_speed" => stats.max_move_speed = positive(value)?,
+        "jump_time" => stats.jump_time = positive(value)?,
+        "jump_time_per_speed" if value >= 0. => stats.jump_time_per_speed = value,
+        "jump_time_per_speed" => return Err(String::from("`jump_time_per_speed` can't be negative")),
+        _ => return Err(format!("unknown tuning value `{}`", name)),
+    }
+    Ok(())
+}
@@ -0,0 +1,548 @@
+//! `ui` is a small retained UI layer for the menus and the HUD. Widgets are
+//! placed with anchors on the virtual screen, the 1920 x 1080 area the game
+//! is drawn in, so a menu anchored to the bottom right corner stays there
+//! whatever its size. Text is drawn with `TextCached`, with a drop shadow
+//! unless its style turns it off.
+//!
+//! A `Label` is a piece of text, wrapped at a width if it is given one. A
+//! `List` is a vertical menu of headings, buttons, sliders and toggles, one
+//! of which has the focus. The focus is moved with the keyboard or a
+//! gamepad's d-pad, and what the player does with the focused widget comes
+//! back as a `UiEvent`.
+
+use ggez::event::{Button, Keycode};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Font, HorizontalAlign as HAlign, Layout, Point2, Rect, Scale,
+                     TextCached, Vector2};
+use ggez::{Context, GameResult};
+
+/// The size of the virtual screen widgets are placed on.
+pub const SCREEN_WIDTH: f32 = 1920.;
+pub const SCREEN_HEIGHT: f32 = 1080.;
+
+/// The gap between a slider or toggle's text and the control itself.
+const CONTROL_GAP: f32 = 40.;
+const SLIDER_WIDTH: f32 = 300.;
+const SLIDER_HEIGHT: f32 = 8.;
+const SLIDER_KNOB: f32 = 24.;
+
+/// `Anchor` is the point of the screen a widget is placed relative to. The
+/// same point of the widget goes there, so a widget anchored to the top right
+/// has its top right corner in the top right of the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// How far across and down the screen the anchor is, from 0 to 1.
+    fn fractions(self) -> (f32, f32) {
+        match self {
+            Anchor::TopLeft => (0., 0.),
+            Anchor::Top => (0.5, 0.),
+            Anchor::TopRight => (1., 0.),
+            Anchor::Left => (0., 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1., 0.5),
+            Anchor::BottomLeft => (0., 1.),
+            Anchor::Bottom => (0.5, 1.),
+            Anchor::BottomRight => (1., 1.),
+        }
+    }
+
+    /// The top left corner of a widget of `size` at this anchor, moved by
+    /// `offset` (with Y increasing downwards, like the screen).
+    pub fn place(self, size: Vector2, offset: Vector2) -> Point2 {
+        let (x, y) = self.fractions();
+        Point2::new(x * (SCREEN_WIDTH - size.x) + offset.x, y * (SCREEN_HEIGHT - size.y) + offset.y)
+    }
+}
+
+/// A drop shadow: the text again, offset and in another color, behind it.
+#[derive(Clone, Copy, Debug)]
+pub struct Shadow {
+    pub offset: Vector2,
+    pub color: Color,
+}
+
+impl Default for Shadow {
+    /// The dark shadow used by most of the game's text.
+    fn default() -> Shadow {
+        Shadow { offset: Vector2::new(3., 3.), color: Color::from((0, 0, 0, 255)) }
+    }
+}
+
+/// How text is drawn.
+#[derive(Clone)]
+pub struct TextStyle {
+    pub font: Font,
+    pub size: f32,
+    pub color: Color,
+    pub shadow: Option<Shadow>,
+}
+
+impl TextStyle {
+    /// A style with the default shadow.
+    pub fn new(font: &Font, size: f32, color: Color) -> TextStyle {
+        TextStyle { font: font.clone(), size, color, shadow: Some(Shadow::default()) }
+    }
+
+    pub fn shadow(mut self, shadow: Option<Shadow>) -> TextStyle {
+        self.shadow = shadow;
+        self
+    }
+
+    fn text(&self, contents: &str) -> GameResult<TextCached> {
+        let mut text = TextCached::new(contents)?;
+        text.set_font(self.font.clone(), Scale::uniform(self.size));
+        Ok(text)
+    }
+}
+
+/// Queues `text` at `dest` in `color`, behind its shadow if it has one. The
+/// caller is responsible for calling `TextCached::draw_queued()`.
+pub fn queue_text(ctx: &mut Context, text: &TextCached, dest: Point2, color: Color, shadow: Option<Shadow>) {
+    if let Some(shadow) = shadow {
+        text.queue(ctx, dest + shadow.offset, Some(shadow.color));
+    }
+    text.queue(ctx, dest, Some(color));
+}
+
+fn text_size(ctx: &mut Context, text: &TextCached) -> Vector2 {
+    Vector2::new(text.width(ctx) as f32, text.height(ctx) as f32)
+}
+
+/// `Label` is a piece of text placed on the screen.
+#[derive(Clone)]
+pub struct Label {
+    text: String,
+    style: TextStyle,
+    anchor: Anchor,
+    offset: Vector2,
+    /// The width the text is wrapped at, if it is.
+    wrap: Option<f32>,
+    centered: bool,
+}
+
+impl Label {
+    /// A label in the top left corner of the screen.
+    pub fn new<S: Into<String>>(text: S, style: TextStyle) -> Label {
+        Label { text: text.into(), style, anchor: Anchor::TopLeft, offset: Vector2::new(0., 0.), wrap: None, centered: false }
+    }
+
+    pub fn anchored(mut self, anchor: Anchor, offset: Vector2) -> Label {
+        self.anchor = anchor;
+        self.offset = offset;
+        self
+    }
+
+    /// Wraps the text onto a new line wherever it would be wider than `width`.
+    pub fn wrapped(mut self, width: f32) -> Label {
+        self.wrap = Some(width);
+        self
+    }
+
+    /// Centers each line of wrapped text, rather than lining them up on the left.
+    pub fn centered(mut self) -> Label {
+        self.centered = true;
+        self
+    }
+
+    pub fn set_text<S: Into<String>>(&mut self, text: S) {
+        self.text = text.into();
+    }
+
+    /// Queues the label to be drawn. The caller is responsible for calling
+    /// `TextCached::draw_queued()`.
+    pub fn queue(&self, ctx: &mut Context) -> GameResult<()> {
+        let mut text = self.style.text(&self.text)?;
+        if let Some(width) = self.wrap {
+            let align = if self.centered { HAlign::Center } else { HAlign::Left };
+            text.set_bounds(Point2::new(width, SCREEN_HEIGHT), Some(Layout::default().h_align(align)));
+        }
+        let size = text_size(ctx, &text);
+        let dest = self.anchor.place(size, self.offset);
+        queue_text(ctx, &text, dest, self.style.color, self.style.shadow);
+        Ok(())
+    }
+}
+
+/// `UiInput` is a menu action, from the keyboard or a gamepad.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Activate,
+    Back,
+}
+
+impl UiInput {
+    pub fn from_keycode(keycode: Keycode) -> Option<UiInput> {
+        match keycode {
+            Keycode::Up => Some(UiInput::Up),
+            Keycode::Down => Some(UiInput::Down),
+            Keycode::Left => Some(UiInput::Left),
+            Keycode::Right => Some(UiInput::Right),
+            Keycode::Return | Keycode::Space => Some(UiInput::Activate),
+            Keycode::Escape => Some(UiInput::Back),
+            _ => None,
+        }
+    }
+
+    pub fn from_button(button: Button) -> Option<UiInput> {
+        match button {
+            Button::DPadUp => Some(UiInput::Up),
+            Button::DPadDown => Some(UiInput::Down),
+            Button::DPadLeft => Some(UiInput::Left),
+            Button::DPadRight => Some(UiInput::Right),
+            Button::A | Button::Start => Some(UiInput::Activate),
+            Button::B | Button::Back => Some(UiInput::Back),
+            _ => None,
+        }
+    }
+}
+
+/// `UiEvent` is what the player did with a menu, naming the widget by its id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UiEvent {
+    Pressed(&'static str),
+    /// A slider was moved to the given value.
+    Changed(&'static str, f32),
+    Toggled(&'static str, bool),
+    /// The player backed out of the menu.
+    Back,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum WidgetKind {
+    /// Text that can't have the focus, such as a section title.
+    Heading,
+    Button,
+    Slider { value: f32, min: f32, max: f32, step: f32 },
+    Toggle(bool),
+}
+
+#[derive(Clone, Debug)]
+struct Widget {
+    id: &'static str,
+    text: String,
+    kind: WidgetKind,
+    /// The color drawn in without the focus, if not the list's.
+    color: Option<Color>,
+}
+
+impl Widget {
+    fn focusable(&self) -> bool {
+        self.kind != WidgetKind::Heading
+    }
+}
+
+/// `List` is a vertical menu of widgets, each with an id to find it by. The
+/// focused widget is drawn in the focus color between markers.
+#[derive(Clone)]
+pub struct List {
+    widgets: Vec<Widget>,
+    focus: usize,
+    style: TextStyle,
+    focus_color: Color,
+    anchor: Anchor,
+    offset: Vector2,
+    spacing: f32,
+    centered: bool,
+}
+
+impl List {
+    /// An empty list in the top left corner of the screen.
+    pub fn new(style: TextStyle, focus_color: Color) -> List {
+        List {
+            widgets: Vec::new(),
+            focus: 0,
+            style,
+            focus_color,
+            anchor: Anchor::TopLeft,
+            offset: Vector2::new(0., 0.),
+            spacing: 20.,
+            centered: false,
+        }
+    }
+
+    pub fn anchored(mut self, anchor: Anchor, offset: Vector2) -> List {
+        self.anchor = anchor;
+        self.offset = offset;
+        self
+    }
+
+    /// Sets the space between the widgets.
+    pub fn spacing(mut self, spacing: f32) -> List {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Centers each widget in the list, rather than lining them up on the left.
+    pub fn centered(mut self) -> List {
+        self.centered = true;
+        self
+    }
+
+    pub fn heading<S: Into<String>>(self, id: &'static str, text: S) -> List {
+        self.with(id, text.into(), WidgetKind::Heading)
+    }
+
+    pub fn button<S: Into<String>>(self, id: &'static str, text: S) -> List {
+        self.with(id, text.into(), WidgetKind::Button)
+    }
+
+    /// A slider from `min` to `max`, moved `step` at a time.
+    pub fn slider<S: Into<String>>(self, id: &'static str, text: S, value: f32, min: f32, max: f32, step: f32) -> List {
+        let value = value.max(min).min(max);
+        self.with(id, text.into(), WidgetKind::Slider { value, min, max, step })
+    }
+
+    pub fn toggle<S: Into<String>>(self, id: &'static str, text: S, on: bool) -> List {
+        self.with(id, text.into(), WidgetKind::Toggle(on))
+    }
+
+    fn with(mut self, id: &'static str, text: String, kind: WidgetKind) -> List {
+        self.widgets.push(Widget { id, text, kind, color: None });
+        // The focus starts on the first widget that can have it
+        if !self.widgets[self.focus].focusable() {
+            self.focus = self.widgets.len() - 1;
+        }
+        self
+    }
+
+    /// Draws the widget added last in `color` while it doesn't have the focus.
+    pub fn colored(mut self, color: Color) -> List {
+        if let Some(widget) = self.widgets.last_mut() {
+            widget.color = Some(color);
+        }
+        self
+    }
+
+    /// The id of the widget with the focus, if any widget can have it.
+    pub fn focused(&self) -> Option<&'static str> {
+        self.widgets.get(self.focus).filter(|widget| widget.focusable()).map(|widget| widget.id)
+    }
+
+    /// Moves the focus to the widget `id`, if it can have it.
+    pub fn focus(&mut self, id: &str) {
+        if let Some(index) = self.widgets.iter().position(|widget| widget.id == id && widget.focusable()) {
+            self.focus = index;
+        }
+    }
+
+    /// The id of the widget with the focus, and how many widgets before it
+    /// share that id. This tells the rows of a list apart when they share an
+    /// id, such as the levels of a level select.
+    pub fn focused_row(&self) -> Option<(&'static str, usize)> {
+        let id = self.focused()?;
+        Some((id, self.widgets[..self.focus].iter().filter(|widget| widget.id == id).count()))
+    }
+
+    /// Moves the focus to the `row`th widget with the id `id`, if it can have it.
+    pub fn focus_row(&mut self, id: &str, row: usize) {
+        let index = self.widgets.iter().enumerate().filter(|&(_, widget)| widget.id == id).nth(row).map(|(i, _)| i);
+        if let Some(index) = index.filter(|&i| self.widgets[i].focusable()) {
+            self.focus = index;
+        }
+    }
+
+    /// Moves the focus `step` focusable widgets on, wrapping around.
+    fn move_focus(&mut self, step: isize) {
+        let count = self.widgets.len() as isize;
+        let mut index = self.focus as isize;
+        for _ in 0..count {
+            index = (index + step + count) % count;
+            if self.widgets[index as usize].focusable() {
+                self.focus = index as usize;
+                return;
+            }
+        }
+    }
+
+    /// `input()` handles a menu action: up and down move the focus, left and
+    /// right move sliders and flip toggles, and activating presses buttons
+    /// and flips toggles.
+    pub fn input(&mut self, input: UiInput) -> Option<UiEvent> {
+        match input {
+            UiInput::Up => self.move_focus(-1),
+            UiInput::Down => self.move_focus(1),
+            UiInput::Back => return Some(UiEvent::Back),
+            UiInput::Left | UiInput::Right | UiInput::Activate => {
+                let widget = self.widgets.get_mut(self.focus)?;
+                match (widget.kind, input) {
+                    (WidgetKind::Button, UiInput::Activate) => return Some(UiEvent::Pressed(widget.id)),
+                    (WidgetKind::Toggle(on), _) => {
+                        widget.kind = WidgetKind::Toggle(!on);
+                        return Some(UiEvent::Toggled(widget.id, !on));
+                    }
+                    (WidgetKind::Slider { value, min, max, step }, UiInput::Left) | (WidgetKind::Slider { value, min, max, step }, UiInput::Right) => {
+                        let step = if input == UiInput::Left { -step } else { step };
+                        let moved = (value + step).max(min).min(max);
+                        if moved != value {
+                            widget.kind = WidgetKind::Slider { value: moved, min, max, step: step.abs() };
+                            return Some(UiEvent::Changed(widget.id, moved));
+                        }
+                    }
+                    _ => {},
+                }
+            }
+        }
+        None
+    }
+
+    /// The width taken by a widget's control, beside its text.
+    fn control_width(&self, kind: WidgetKind) -> f32 {
+        match kind {
+            WidgetKind::Slider { .. } => CONTROL_GAP + SLIDER_WIDTH,
+            WidgetKind::Toggle(_) => CONTROL_GAP + self.style.size,
+            WidgetKind::Heading | WidgetKind::Button => 0.,
+        }
+    }
+
+    /// Draws the list, flushing the text queued with it.
+    pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+        let mut rows = Vec::with_capacity(self.widgets.len());
+        for (i, widget) in self.widgets.iter().enumerate() {
+            let focused = i == self.focus && widget.focusable();
+            let contents = if focused { format!("> {} <", widget.text) } else { widget.text.clone() };
+            let text = self.style.text(&contents)?;
+            let size = text_size(ctx, &text);
+            rows.push((text, size, focused));
+        }
+        let width = rows.iter().zip(&self.widgets)
+            .fold(0., |width: f32, (&(_, size, _), widget)| width.max(size.x + self.control_width(widget.kind)));
+        let height = rows.iter().fold(0., |height, &(_, size, _)| height + size.y + self.spacing) - self.spacing;
+        let origin = self.anchor.place(Vector2::new(width, height.max(0.)), self.offset);
+
+        let mut y = origin.y;
+        for (&(ref text, size, focused), widget) in rows.iter().zip(&self.widgets) {
+            let row_width = size.x + self.control_width(widget.kind);
+            let x = if self.centered { origin.x + (width - row_width) / 2. } else { origin.x };
+            let color = if focused { self.focus_color } else { widget.color.unwrap_or(self.style.color) };
+            queue_text(ctx, text, Point2::new(x, y), color, self.style.shadow);
+
+            let control = Point2::new(x + size.x + CONTROL_GAP, y);
+            match widget.kind {
+                WidgetKind::Slider { value, min, max, .. } => {
+                    let fraction = if max > min { (value - min) / (max - min) } else { 0. };
+                    let middle = control.y + size.y / 2.;
+                    graphics::set_color(ctx, self.style.color)?;
+                    graphics::rectangle(ctx, DrawMode::Fill, Rect::new(control.x, middle - SLIDER_HEIGHT / 2., SLIDER_WIDTH, SLIDER_HEIGHT))?;
+                    graphics::set_color(ctx, color)?;
+                    let knob = Rect::new(control.x + fraction * SLIDER_WIDTH - SLIDER_KNOB / 2., middle - SLIDER_KNOB / 2., SLIDER_KNOB, SLIDER_KNOB);
+                    graphics::rectangle(ctx, DrawMode::Fill, knob)?;
+                }
+                WidgetKind::Toggle(on) => {
+                    let side = self.style.size * 0.8;
+                    let check = Rect::new(control.x, control.y + (size.y - side) / 2., side, side);
+                    graphics::set_color(ctx, color)?;
+                    graphics::rectangle(ctx, DrawMode::Line(3.), check)?;
+                    if on {
+                        let inset = side / 4.;
+                        graphics::rectangle(ctx, DrawMode::Fill, Rect::new(check.x + inset, check.y + inset, side - 2. * inset, side - 2. * inset))?;
+                    }
+                }
+                WidgetKind::Heading | WidgetKind::Button => {},
+            }
+            y += size.y + self.spacing;
+        }
+        graphics::set_color(ctx, Color::new(1.0, 1.0, 1.0, 1.0))?;
+        TextCached::draw_queued(ctx, DrawParam::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ggez::graphics::FontId;
+
+    fn list() -> List {
+        let style = TextStyle::new(&Font::GlyphFont(FontId(0)), 20., Color::new(1., 1., 1., 1.));
+        List::new(style, Color::new(1., 0., 0., 1.))
+    }
+
+    #[test]
+    fn anchors_place_widgets_inside_the_screen() {
+        let size = Vector2::new(100., 50.);
+        assert_eq!(Anchor::TopLeft.place(size, Vector2::new(10., 20.)), Point2::new(10., 20.));
+        assert_eq!(Anchor::Center.place(size, Vector2::new(0., 0.)), Point2::new(910., 515.));
+        assert_eq!(Anchor::BottomRight.place(size, Vector2::new(-10., -10.)), Point2::new(1810., 1020.));
+        assert_eq!(Anchor::Bottom.place(size, Vector2::new(0., 0.)), Point2::new(910., 1030.));
+    }
+
+    #[test]
+    fn focus_starts_on_the_first_focusable_widget() {
+        assert_eq!(list().focused(), None);
+        assert_eq!(list().heading("title", "Title").focused(), None);
+        let mut menu = list().heading("title", "Title").heading("subtitle", "Subtitle").button("a", "A").button("b", "B");
+        assert_eq!(menu.focused(), Some("a"));
+        menu.focus("title");
+        assert_eq!(menu.focused(), Some("a"));
+        menu.focus("b");
+        assert_eq!(menu.focused(), Some("b"));
+    }
+
+    #[test]
+    fn focus_skips_headings_and_wraps_around() {
+        let mut menu = list().heading("title", "Title").button("a", "A").heading("more", "More").button("b", "B");
+        assert_eq!(menu.input(UiInput::Down), None);
+        assert_eq!(menu.focused(), Some("b"));
+        menu.input(UiInput::Down);
+        assert_eq!(menu.focused(), Some("a"));
+        menu.input(UiInput::Up);
+        assert_eq!(menu.focused(), Some("b"));
+        menu.input(UiInput::Up);
+        assert_eq!(menu.focused(), Some("a"));
+    }
+
+    #[test]
+    fn rows_sharing_an_id_are_told_apart() {
+        let mut menu = list().heading("world", "Beach").button("level", "1").button("level", "2")
+            .heading("world", "Caves").button("level", "3").button("back", "Back");
+        assert_eq!(menu.focused_row(), Some(("level", 0)));
+        menu.input(UiInput::Down);
+        menu.input(UiInput::Down);
+        assert_eq!(menu.focused_row(), Some(("level", 2)));
+        menu.focus_row("level", 1);
+        assert_eq!(menu.focused_row(), Some(("level", 1)));
+        menu.focus_row("level", 3);
+        menu.focus_row("world", 1);
+        assert_eq!(menu.focused_row(), Some(("level", 1)));
+        menu.input(UiInput::Up);
+        menu.input(UiInput::Up);
+        assert_eq!(menu.focused_row(), Some(("back", 0)));
+    }
+
+    #[test]
+    fn sliders_are_clamped() {
+        let mut menu = list().slider("volume", "Volume", 1.5, 0., 1., 0.25);
+        assert_eq!(menu.input(UiInput::Right), None);
+        assert_eq!(menu.input(UiInput::Left), Some(UiEvent::Changed("volume", 0.75)));
+        let mut menu = list().slider("volume", "Volume", 0.1, 0., 1., 0.25);
+        assert_eq!(menu.input(UiInput::Left), Some(UiEvent::Changed("volume", 0.)));
+        assert_eq!(menu.input(UiInput::Left), None);
+        assert_eq!(menu.input(UiInput::Activate), None);
+    }
+
+    #[test]
+    fn toggles_flip_and_buttons_press() {
+        let mut menu = list().toggle("ghost", "Ghost", true).button("back", "Back");
+        assert_eq!(menu.input(UiInput::Activate), Some(UiEvent::Toggled("ghost", false)));
+        assert_eq!(menu.input(UiInput::Right), Some(UiEvent::Toggled("ghost", true)));
+        assert_eq!(menu.input(UiInput::Left), Some(UiEvent::Toggled("ghost", false)));
+        menu.input(UiInput::Down);
+        assert_eq!(menu.input(UiInput::Left), None);
+        assert_eq!(menu.input(UiInput::Activate), Some(UiEvent::Pressed("back")));
+        assert_eq!(menu.input(UiInput::Back), Some(UiEvent::Back));
+    }
+}
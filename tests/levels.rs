@@ -0,0 +1,35 @@
+//! Checks that every level shipped in `resources/levels` can be finished,
+//! and that the level generator only makes levels that can be.
+
+extern crate game;
+
+use std::fs;
+use std::path::Path;
+
+use game::actors::player::base_stats;
+use game::level::Level;
+use game::tuning::TuningProfiles;
+use game::{generator, reachability};
+
+#[test]
+fn shipped_levels_can_be_finished() {
+    let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources");
+    let tuning = TuningProfiles::parse(&fs::read_to_string(resources.join("tuning.txt")).unwrap()).unwrap();
+    for entry in fs::read_dir(resources.join("levels")).unwrap() {
+        let path = entry.unwrap().path();
+        let level = Level::parse(&fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        let (_, stats) = tuning.select(&[level.tuning.as_ref().map(|n| n.as_str())]);
+        reachability::assert_solvable(&level, &stats);
+    }
+}
+
+#[test]
+fn generated_levels_can_be_finished() {
+    let stats = base_stats();
+    for seed in 0..20 {
+        let level = generator::generate(seed, &stats).unwrap();
+        reachability::assert_solvable(&level, &stats);
+        assert_eq!(level.serialize(), generator::generate(seed, &stats).unwrap().serialize());
+    }
+}